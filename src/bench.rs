@@ -0,0 +1,100 @@
+use ::phi::data::{Grid, Rectangle};
+use ::phi::rng::Rng;
+use ::std::time::Instant;
+
+// Consts
+const SEED: u32 = 21;
+const FRAMES: usize = 600;
+const BULLETS: usize = 5000;
+const TARGETS: usize = 300;
+const WINDOW: (f64, f64) = (800.0, 600.0);
+const CELL_SIZE: f64 = 64.0;
+/// The time there is to spend on a frame, at 60 frames per second.
+const FRAME_BUDGET: f64 = 1000.0 / 60.0;
+
+
+/// A rectangle moving across the window, wrapping around its edges.
+struct Body {
+    rect: Rectangle,
+    vel: (f64, f64),
+}
+
+impl Body {
+    fn random(rng: &mut Rng, w: f64, h: f64, speed: f64) -> Body {
+        Body {
+            rect: Rectangle {
+                x: rng.range(0.0, WINDOW.0 - w),
+                y: rng.range(0.0, WINDOW.1 - h),
+                w: w,
+                h: h,
+            },
+            vel: (rng.range(-speed, speed), rng.range(-speed, speed)),
+        }
+    }
+
+    fn update(&mut self, dt: f64) {
+        self.rect.x = (self.rect.x + self.vel.0 * dt + WINDOW.0) % WINDOW.0;
+        self.rect.y = (self.rect.y + self.vel.1 * dt + WINDOW.1) % WINDOW.1;
+    }
+}
+
+/// Times finding the overlaps between many bullets and targets, once with
+/// `Grid` and once by testing every pair, and checks that both agree.
+/// Nothing is drawn, so it runs without SDL.
+pub fn collisions() {
+    let mut rng = Rng::new(SEED);
+    let mut bullets: Vec<Body> = (0..BULLETS).map(|_| Body::random(&mut rng, 8.0, 4.0, 600.0)).collect();
+    let mut targets: Vec<Body> = (0..TARGETS).map(|_| Body::random(&mut rng, 40.0, 40.0, 200.0)).collect();
+
+    let mut grid = Grid::new(Rectangle::with_size(WINDOW.0, WINDOW.1), CELL_SIZE);
+    let mut grid_time = 0.0;
+    let mut brute_time = 0.0;
+    let mut hits = 0;
+
+    for frame in 0..FRAMES {
+        for body in bullets.iter_mut().chain(targets.iter_mut()) {
+            body.update(1.0 / 60.0);
+        }
+        let bullet_rects: Vec<Rectangle> = bullets.iter().map(|body| body.rect).collect();
+        let target_rects: Vec<Rectangle> = targets.iter().map(|body| body.rect).collect();
+
+        let start = Instant::now();
+        grid.reset(Rectangle::with_size(WINDOW.0, WINDOW.1));
+        for (i, &rect) in bullet_rects.iter().enumerate() {
+            grid.insert(i, rect);
+        }
+        let grid_hits = grid.pairs(&target_rects).into_iter()
+            .filter(|&(target, bullet)| target_rects[target].overlaps(bullet_rects[bullet]))
+            .count();
+        grid_time += millis(start);
+
+        let start = Instant::now();
+        let mut brute_hits = 0;
+        for target in &target_rects {
+            for bullet in &bullet_rects {
+                if target.overlaps(*bullet) {
+                    brute_hits += 1;
+                }
+            }
+        }
+        brute_time += millis(start);
+
+        if grid_hits != brute_hits {
+            println!("Frame {}: the grid found {} hits instead of {}", frame, grid_hits, brute_hits);
+            ::std::process::exit(1);
+        }
+        hits += grid_hits;
+    }
+
+    println!("{} bullets against {} targets, {} frames, {} hits", BULLETS, TARGETS, FRAMES, hits);
+    for &(name, time) in &[("grid", grid_time), ("brute force", brute_time)] {
+        let per_frame = time / FRAMES as f64;
+        println!("{:>12}: {:.3} ms/frame ({:.1}% of the {:.1} ms budget)",
+                 name, per_frame, 100.0 * per_frame / FRAME_BUDGET, FRAME_BUDGET);
+    }
+}
+
+fn millis(start: Instant) -> f64 {
+    let elapsed = start.elapsed();
+    elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1_000_000.0
+}
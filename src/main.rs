@@ -1,13 +1,74 @@
 extern crate rand;
+extern crate rustc_serialize;
 extern crate sdl2;
 extern crate sdl2_image;
 extern crate sdl2_ttf;
 
+#[cfg(feature = "bench")]
+mod bench;
 mod phi;
 mod views;
 
 fn main() {
-    ::phi::spawn("ArcadeRS Shooter", |phi| {
-        Box::new(::views::main_menu::MainMenuView::new(phi))
-    });
+    let init = |phi: &mut ::phi::Phi| -> Result<Box<::phi::View>, ::phi::Error> {
+        let menu = try!(::views::main_menu::MainMenuView::new(phi));
+        Ok(Box::new(menu))
+    };
+
+    let mut config = ::phi::Config::new("ArcadeRS Shooter");
+
+    let mut args = ::std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            // runs the game offscreen, e.g. on CI
+            "--headless" => config.headless = Some(value(&mut args, &arg)),
+            "--max-fps" => config.max_fps = Some(value(&mut args, &arg)),
+            "--record" => config.record = Some(path(&mut args, &arg)),
+            "--replay" => config.replay = Some(path(&mut args, &arg)),
+            "--seed" => config.seed = Some(value(&mut args, &arg)),
+            // measures the collision checks, without opening a window
+            #[cfg(feature = "bench")]
+            "--bench-collisions" => return ::bench::collisions(),
+            "--hot-reload" => config.hot_reload = true,
+            "--volume" => config.volume = value(&mut args, &arg),
+            "--sfx-volume" => config.sfx_volume = value(&mut args, &arg),
+            "--music-volume" => config.music_volume = value(&mut args, &arg),
+            _ => println!("Ignoring unknown argument `{}`", arg),
+        }
+    }
+
+    if let Err(e) = ::phi::spawn(config, init) {
+        println!("{}", e);
+        ::std::process::exit(1);
+    }
+}
+
+/// Parses the argument which follows `flag`, exiting if it is missing or
+/// invalid. It is only consumed if it is valid, so that a missing value
+/// is reported as such rather than swallowing the next flag.
+fn value<T, I>(args: &mut ::std::iter::Peekable<I>, flag: &str) -> T
+where T: ::std::str::FromStr, I: Iterator<Item = String> {
+    if let Some(value) = args.peek().and_then(|arg| arg.parse().ok()) {
+        args.next();
+        return value;
+    }
+
+    match args.peek() {
+        Some(arg) => println!("Invalid value `{}` for `{}`", arg, flag),
+        None => println!("Missing value for `{}`", flag),
+    }
+    ::std::process::exit(1);
+}
+
+/// The path which follows `flag`, exiting if it is missing. Another flag is
+/// not taken for a path, so that `--record --seed 3` does not record into
+/// a file named `--seed`.
+fn path<I>(args: &mut ::std::iter::Peekable<I>, flag: &str) -> String
+where I: Iterator<Item = String> {
+    if args.peek().map_or(false, |arg| !arg.starts_with("--")) {
+        return args.next().unwrap();
+    }
+
+    println!("Missing value for `{}`", flag);
+    ::std::process::exit(1);
 }
@@ -0,0 +1,270 @@
+use ::phi::Error;
+use ::phi::backend::Backend;
+use ::phi::data::{Mask, Rectangle};
+use ::phi::gfx::Sprite;
+use ::sdl2::pixels::PixelFormatEnum;
+use ::sdl2::render::Texture;
+use ::sdl2::surface::Surface;
+use ::sdl2_image::LoadSurface;
+use ::std::cell::RefCell;
+use ::std::collections::HashMap;
+use ::std::fs;
+use ::std::path::Path;
+use ::std::rc::Rc;
+use ::std::time::SystemTime;
+
+/// How often, in seconds, the watched files are checked for changes.
+const POLL_INTERVAL: f64 = 1.0;
+/// How opaque a pixel must be to be solid in a mask.
+const MASK_THRESHOLD: u8 = 128;
+
+/// Keeps track of the textures which were loaded from disk, so that each
+/// image is only ever loaded once, however many views use it. Sprites are
+/// the handles to these textures: cloning one is cheap, and a texture is
+/// kept around for as long as a sprite refers to it.
+///
+/// During development, `hot_reload` makes phi watch the files which were
+/// loaded, and swap in their new version whenever they change. The masks
+/// made from an image are made again along with it.
+pub struct Assets {
+    textures: HashMap<String, Rc<RefCell<Texture>>>,
+    /// The masks made from every image, with the region each was made from.
+    masks: HashMap<String, Vec<(Rectangle, Mask)>>,
+    /// When the watched files were last modified.
+    modified: HashMap<String, SystemTime>,
+    since_poll: f64,
+    pub hot_reload: bool,
+}
+
+impl Assets {
+    pub fn new() -> Assets {
+        Assets {
+            textures: HashMap::new(),
+            masks: HashMap::new(),
+            modified: HashMap::new(),
+            since_poll: 0.0,
+            hot_reload: false,
+        }
+    }
+
+    /// The whole image at `path`, which is only loaded the first time.
+    pub fn sprite(&mut self, renderer: &Backend, path: &str) -> Result<Sprite, Error> {
+        if let Some(texture) = self.textures.get(path) {
+            return Ok(Sprite::shared(texture.clone()));
+        }
+
+        let texture = try!(renderer.load_texture(path)
+            .map_err(|e| Error::Asset(format!("Could not load {}: {}", path, e))));
+        let texture = Rc::new(RefCell::new(texture));
+        self.textures.insert(path.to_string(), texture.clone());
+        self.watch(path);
+        Ok(Sprite::shared(texture))
+    }
+
+    /// Which pixels of every region of the image at `path` are solid. They
+    /// are only read the first time, and again when the image is reloaded.
+    pub fn masks(&mut self, path: &str, regions: &[Rectangle]) -> Result<Vec<Mask>, Error> {
+        {
+            let known = self.masks.get(path).map(|masks| &masks[..]).unwrap_or(&[]);
+            let cached: Vec<Mask> = regions.iter()
+                .filter_map(|region| known.iter().find(|&&(known, _)| known == *region))
+                .map(|&(_, ref mask)| mask.clone())
+                .collect();
+            if cached.len() == regions.len() {
+                return Ok(cached);
+            }
+        }
+
+        let masks = try!(read_masks(path, regions));
+        self.masks.entry(path.to_string()).or_insert(vec![])
+            .extend(regions.iter().cloned().zip(masks.iter().cloned()));
+        self.watch(path);
+        Ok(masks)
+    }
+
+    /// Remembers when the file at `path` was last modified, so that `changed`
+    /// can tell when it is edited.
+    pub fn watch(&mut self, path: &str) {
+        if let Some(time) = modified(path) {
+            self.modified.insert(path.to_string(), time);
+        }
+    }
+
+    /// The watched files which were modified since the last time they were
+    /// checked. They are only checked every `POLL_INTERVAL`, `dt` being the
+    /// time elapsed since the last call.
+    pub fn changed(&mut self, dt: f64) -> Vec<String> {
+        self.since_poll += dt;
+        if self.since_poll < POLL_INTERVAL {
+            return vec![];
+        }
+        self.since_poll = 0.0;
+
+        let mut changed = vec![];
+        for (path, time) in &mut self.modified {
+            match modified(path) {
+                Some(new_time) if new_time != *time => {
+                    *time = new_time;
+                    changed.push(path.clone());
+                },
+                _ => {},
+            }
+        }
+        changed
+    }
+
+    /// Loads the texture at `path` again, so that every sprite using it
+    /// shows the new version, and reads the pixels of its masks again.
+    /// Does nothing if it was never loaded.
+    pub fn reload(&mut self, renderer: &Backend, path: &str) -> Result<(), Error> {
+        if let Some(texture) = self.textures.get(path) {
+            let new_texture = try!(renderer.load_texture(path)
+                .map_err(|e| Error::Asset(format!("Could not reload {}: {}", path, e))));
+            *texture.borrow_mut() = new_texture;
+        }
+
+        if let Some(masks) = self.masks.get(path) {
+            let regions: Vec<Rectangle> = masks.iter().map(|&(region, _)| region).collect();
+            let new_masks = try!(read_masks(path, &regions));
+            for (&(_, ref mask), new_mask) in masks.iter().zip(new_masks) {
+                mask.replace(new_mask);
+            }
+        }
+        Ok(())
+    }
+
+    /// Unloads the textures which are no longer used by any sprite, and the
+    /// masks which are no longer used by any shape.
+    pub fn collect(&mut self) {
+        let mut unused: Vec<String> = self.textures.iter()
+            .filter(|&(_, texture)| Rc::strong_count(texture) == 1)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &unused {
+            self.textures.remove(path);
+        }
+
+        for (path, masks) in &mut self.masks {
+            masks.retain(|&(_, ref mask)| mask.copies() > 1);
+            if masks.is_empty() {
+                unused.push(path.clone());
+            }
+        }
+        self.masks.retain(|_, masks| !masks.is_empty());
+
+        // an image is watched for as long as something was made from it
+        for path in unused {
+            if !self.textures.contains_key(&path) && !self.masks.contains_key(&path) {
+                self.modified.remove(&path);
+            }
+        }
+    }
+}
+
+/// Which pixels of every region of the image at `path` are solid, read
+/// from the file rather than from the texture, which cannot be read back.
+fn read_masks(path: &str, regions: &[Rectangle]) -> Result<Vec<Mask>, Error> {
+    let error = |e: String| Error::Asset(format!("Could not read the pixels of {}: {}", path, e));
+
+    // every pixel as an `u32`, whose lowest byte is the alpha channel
+    let format = try!(Surface::new(1, 1, PixelFormatEnum::RGBA8888).map_err(&error)).pixel_format();
+    let surface = try!(Surface::from_file(Path::new(path))
+        .and_then(|surface| surface.convert(&format))
+        .map_err(&error));
+    let (w, h, pitch) = (surface.width() as usize, surface.height() as usize, surface.pitch() as usize);
+    let alpha_byte = if cfg!(target_endian = "little") { 0 } else { 3 };
+
+    surface.with_lock(|pixels| {
+        regions.iter().map(|region| {
+            let image = Rectangle::with_size(w as f64, h as f64);
+            if !image.contains(*region) {
+                return Err(Error::Geometry(format!("{} is too small for {:?}", path, region)));
+            }
+
+            let (x0, y0) = (region.x as usize, region.y as usize);
+            let (mask_w, mask_h) = (region.w as usize, region.h as usize);
+            let mut solid = Vec::with_capacity(mask_w * mask_h);
+            for y in y0..y0 + mask_h {
+                for x in x0..x0 + mask_w {
+                    solid.push(pixels[y * pitch + x * 4 + alpha_byte] >= MASK_THRESHOLD);
+                }
+            }
+            Ok(Mask::new(mask_w, mask_h, solid))
+        }).collect()
+    })
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use ::phi::data::Rectangle;
+    use ::phi::testing::headless;
+    use ::std::fs;
+    use ::std::path::PathBuf;
+    use ::std::time::UNIX_EPOCH;
+    use super::{Assets, POLL_INTERVAL};
+
+    /// A copy of the image at `source`, in a directory of the test's own.
+    fn scratch(test: &str, source: &str) -> String {
+        let dir = ::std::env::temp_dir().join(format!("arcade-rs-{}-{}", ::std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path: PathBuf = dir.join("image.png");
+        fs::copy(source, &path).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn only_reports_changes_once_per_interval() {
+        let path = scratch("changed", "assets/enemy.png");
+        let mut assets = Assets::new();
+        assets.watch(&path);
+        assert_eq!(assets.changed(POLL_INTERVAL), Vec::<String>::new());
+
+        // as if the file had been saved since it was watched
+        assets.modified.insert(path.clone(), UNIX_EPOCH);
+        assert_eq!(assets.changed(POLL_INTERVAL / 2.0), Vec::<String>::new());
+        assert_eq!(assets.changed(POLL_INTERVAL / 2.0), vec![path.clone()]);
+        assert_eq!(assets.changed(POLL_INTERVAL), Vec::<String>::new());
+    }
+
+    #[test]
+    fn ignores_files_which_were_deleted() {
+        let path = scratch("deleted", "assets/enemy.png");
+        let mut assets = Assets::new();
+        assets.watch(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(assets.changed(POLL_INTERVAL), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reloading_reads_the_masks_again() {
+        let path = scratch("masks", "assets/enemy.png");
+        let region = Rectangle::with_size(40.0, 40.0);
+
+        headless(1, |phi| {
+            let masks = phi.assets.masks(&path, &[region]).unwrap();
+            assert!(masks[0].bounds().w > 0.0);
+
+            // the top left corner of the boss is empty
+            fs::copy("assets/boss.png", &path).unwrap();
+            phi.assets.reload(&*phi.renderer, &path).unwrap();
+            assert_eq!(masks[0].bounds(), Rectangle::with_size(0.0, 0.0));
+            assert_eq!(phi.assets.masks(&path, &[region]).unwrap(), masks);
+        });
+    }
+
+    #[test]
+    fn reloading_what_was_never_loaded_does_nothing() {
+        let path = scratch("never", "assets/enemy.png");
+
+        headless(1, |phi| {
+            phi.assets.reload(&*phi.renderer, &path).unwrap();
+            assert!(phi.assets.masks.is_empty());
+        });
+    }
+}
@@ -0,0 +1,473 @@
+use ::phi::Error;
+use ::sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired, AudioSubsystem};
+use ::std::collections::HashMap;
+use ::std::fs::File;
+use ::std::io::Read;
+use ::std::sync::Arc;
+
+/// How many sound effects can play at the same time. Starting another one
+/// cuts the one which has been playing for the longest.
+pub const MAX_CHANNELS: usize = 8;
+
+/// The rate at which sounds are decoded when there is no audio device.
+const DEFAULT_FREQ: i32 = 44_100;
+
+
+/// A decoded sound, ready to be played. Cloning it is cheap.
+#[derive(Clone)]
+pub struct Sound {
+    /// Interleaved stereo samples, at the rate of the audio device.
+    samples: Arc<Vec<f32>>,
+}
+
+/// The volumes which can be set independently. The volume of every sound is
+/// the product of `Master` and the volume of its kind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Volume {
+    Master,
+    Sfx,
+    Music,
+}
+
+
+/// A sound being played.
+struct Voice {
+    samples: Arc<Vec<f32>>,
+    /// The next stereo frame to be played.
+    frame: usize,
+    /// Music loops and uses the music volume, sound effects use the sfx one.
+    music: bool,
+}
+
+impl Voice {
+    fn frames(&self) -> usize {
+        self.samples.len() / 2
+    }
+}
+
+/// Mixes the sounds being played, on SDL's audio thread.
+struct Mixer {
+    voices: Vec<Voice>,
+    channels: usize,
+    master: f32,
+    sfx: f32,
+    music: f32,
+    /// Paused music stays where it was, and is not heard.
+    music_paused: bool,
+}
+
+impl Mixer {
+    fn play(&mut self, sound: &Sound) {
+        let playing = self.voices.iter().filter(|voice| !voice.music).count();
+        if playing >= MAX_CHANNELS {
+            let oldest = self.voices.iter().enumerate()
+                .filter(|&(_, voice)| !voice.music)
+                .max_by_key(|&(_, voice)| voice.frame)
+                .map(|(i, _)| i);
+            if let Some(i) = oldest {
+                self.voices.remove(i);
+            }
+        }
+
+        self.voices.push(Voice { samples: sound.samples.clone(), frame: 0, music: false });
+    }
+
+    fn play_music(&mut self, sound: &Sound) {
+        self.stop_music();
+        self.music_paused = false;
+        self.voices.push(Voice { samples: sound.samples.clone(), frame: 0, music: true });
+    }
+
+    fn stop_music(&mut self) {
+        self.voices.retain(|voice| !voice.music);
+    }
+}
+
+impl AudioCallback for Mixer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = 0.0;
+        }
+
+        let channels = self.channels;
+        for voice in &mut self.voices {
+            if voice.music && self.music_paused {
+                continue;
+            }
+            let volume = self.master * if voice.music { self.music } else { self.sfx };
+
+            for frame in out.chunks_mut(channels) {
+                if voice.frame >= voice.frames() {
+                    // an empty sound would loop forever
+                    if voice.music && voice.frames() > 0 {
+                        voice.frame = 0;
+                    } else {
+                        break;
+                    }
+                }
+
+                let left = voice.samples[voice.frame * 2] * volume;
+                let right = voice.samples[voice.frame * 2 + 1] * volume;
+                if channels == 1 {
+                    frame[0] += (left + right) / 2.0;
+                } else {
+                    frame[0] += left;
+                    frame[1] += right;
+                }
+                voice.frame += 1;
+            }
+        }
+
+        self.voices.retain(|voice| voice.music || voice.frame < voice.frames());
+
+        for sample in out.iter_mut() {
+            *sample = sample.max(-1.0).min(1.0);
+        }
+    }
+}
+
+
+/// Plays sound effects and music. Without an audio device, for example when
+/// SDL has no audio driver, sounds still load but nothing is heard; SDL's
+/// `dummy` driver, selected with `SDL_AUDIODRIVER=dummy`, runs the mixer
+/// without any sound card.
+pub struct Audio {
+    _subsystem: Option<AudioSubsystem>,
+    device: Option<AudioDevice<Mixer>>,
+    freq: i32,
+    sounds: HashMap<String, Sound>,
+}
+
+impl Audio {
+    /// An `Audio` which does not play anything.
+    pub fn silent() -> Audio {
+        Audio {
+            _subsystem: None,
+            device: None,
+            freq: DEFAULT_FREQ,
+            sounds: HashMap::new(),
+        }
+    }
+
+    pub fn open(subsystem: AudioSubsystem) -> Result<Audio, Error> {
+        let desired = AudioSpecDesired {
+            freq: Some(DEFAULT_FREQ),
+            channels: Some(2),
+            samples: Some(1024),
+        };
+
+        let mut freq = DEFAULT_FREQ;
+        let device = try!(subsystem.open_playback(None, &desired, |spec| {
+            freq = spec.freq;
+            Mixer {
+                voices: vec![],
+                channels: spec.channels.max(1) as usize,
+                master: 1.0,
+                sfx: 1.0,
+                music: 1.0,
+                music_paused: false,
+            }
+        }).map_err(Error::Sdl));
+        device.resume();
+
+        Ok(Audio {
+            _subsystem: Some(subsystem),
+            device: Some(device),
+            freq: freq,
+            sounds: HashMap::new(),
+        })
+    }
+
+    /// The sound in the WAV file at `path`, which is only loaded the first
+    /// time.
+    pub fn load(&mut self, path: &str) -> Result<Sound, Error> {
+        if let Some(sound) = self.sounds.get(path) {
+            return Ok(sound.clone());
+        }
+
+        let mut bytes = vec![];
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .map_err(|e| Error::Asset(format!("Could not read {}: {}", path, e))));
+
+        let samples = try!(decode_wav(&bytes, self.freq)
+            .map_err(|e| Error::Asset(format!("{}: {}", path, e))));
+        let sound = Sound { samples: Arc::new(samples) };
+        self.sounds.insert(path.to_string(), sound.clone());
+        Ok(sound)
+    }
+
+    /// Plays a sound effect once.
+    pub fn play(&mut self, sound: &Sound) {
+        if let Some(ref mut device) = self.device {
+            device.lock().play(sound);
+        }
+    }
+
+    /// Loops `sound` in place of the current music.
+    pub fn play_music(&mut self, sound: &Sound) {
+        if let Some(ref mut device) = self.device {
+            device.lock().play_music(sound);
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        if let Some(ref mut device) = self.device {
+            device.lock().stop_music();
+        }
+    }
+
+    /// Holds the music where it is, until `resume_music`.
+    pub fn pause_music(&mut self) {
+        if let Some(ref mut device) = self.device {
+            device.lock().music_paused = true;
+        }
+    }
+
+    pub fn resume_music(&mut self) {
+        if let Some(ref mut device) = self.device {
+            device.lock().music_paused = false;
+        }
+    }
+
+    /// Sets one of the volumes, between 0 and 1.
+    pub fn set_volume(&mut self, volume: Volume, value: f64) {
+        let value = value.max(0.0).min(1.0) as f32;
+        if let Some(ref mut device) = self.device {
+            let mut mixer = device.lock();
+            match volume {
+                Volume::Master => mixer.master = value,
+                Volume::Sfx => mixer.sfx = value,
+                Volume::Music => mixer.music = value,
+            }
+        }
+    }
+}
+
+
+/// Decodes an uncompressed 8 or 16 bit, mono or stereo WAV file into
+/// interleaved stereo samples at `freq` samples per second.
+fn decode_wav(bytes: &[u8], freq: i32) -> Result<Vec<f32>, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a WAV file".to_string());
+    }
+
+    let u16_at = |i: usize| bytes[i] as u16 | (bytes[i + 1] as u16) << 8;
+    let u32_at = |i: usize| u16_at(i) as u32 | (u16_at(i + 2) as u32) << 16;
+
+    // (channels, rate, bits)
+    let mut format = None;
+    let mut data = None;
+
+    let mut i = 12;
+    while i + 8 <= bytes.len() {
+        let id = &bytes[i..i + 4];
+        let len = u32_at(i + 4) as usize;
+        let start = i + 8;
+        let end = ::std::cmp::min(start + len, bytes.len());
+
+        if id == b"fmt " && len >= 16 {
+            if start + 16 > bytes.len() {
+                return Err("truncated `fmt ` chunk".to_string());
+            }
+            if u16_at(start) != 1 {
+                return Err("only uncompressed WAV files are supported".to_string());
+            }
+            format = Some((u16_at(start + 2) as usize, u32_at(start + 4), u16_at(start + 14)));
+        } else if id == b"data" {
+            data = Some(&bytes[start..end]);
+        }
+
+        // chunks are padded to an even length
+        i = start + len + len % 2;
+    }
+
+    let (channels, rate, bits) = try!(format.ok_or("missing `fmt ` chunk".to_string()));
+    let data = try!(data.ok_or("missing `data` chunk".to_string()));
+
+    let samples: Vec<f32> = match bits {
+        8 => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        16 => data.chunks(2)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| (pair[0] as u16 | (pair[1] as u16) << 8) as i16 as f32 / 32_768.0)
+            .collect(),
+        _ => return Err(format!("{} bit samples are not supported", bits)),
+    };
+
+    let stereo: Vec<(f32, f32)> = match channels {
+        1 => samples.iter().map(|&s| (s, s)).collect(),
+        2 => samples.chunks(2).filter(|pair| pair.len() == 2).map(|pair| (pair[0], pair[1])).collect(),
+        _ => return Err(format!("{} channels are not supported", channels)),
+    };
+
+    if stereo.is_empty() || rate == 0 {
+        return Ok(vec![]);
+    }
+
+    // resample linearly to the rate of the device
+    let step = rate as f64 / freq as f64;
+    let frames = (stereo.len() as f64 / step) as usize;
+    let mut out = Vec::with_capacity(frames * 2);
+    for n in 0..frames {
+        let pos = n as f64 * step;
+        let i = pos as usize;
+        let next = ::std::cmp::min(i + 1, stereo.len() - 1);
+        let t = (pos - i as f64) as f32;
+        out.push(stereo[i].0 + (stereo[next].0 - stereo[i].0) * t);
+        out.push(stereo[i].1 + (stereo[next].1 - stereo[i].1) * t);
+    }
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_CHANNELS, Mixer, Sound, decode_wav};
+    use ::sdl2::audio::AudioCallback;
+    use ::std::sync::Arc;
+
+    fn mixer() -> Mixer {
+        Mixer { voices: vec![], channels: 2, master: 1.0, sfx: 1.0, music: 1.0, music_paused: false }
+    }
+
+    /// A WAV file with a `fmt ` chunk for `format` and a `data` chunk.
+    fn wav(format: u16, channels: u16, rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let u16_le = |n: u16| vec![n as u8, (n >> 8) as u8];
+        let u32_le = |n: u32| vec![n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8];
+
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend(u32_le(36 + data.len() as u32));
+        bytes.extend(b"WAVEfmt ".iter().cloned());
+        bytes.extend(u32_le(16));
+        bytes.extend(u16_le(format));
+        bytes.extend(u16_le(channels));
+        bytes.extend(u32_le(rate));
+        bytes.extend(u32_le(rate * channels as u32 * bits as u32 / 8));
+        bytes.extend(u16_le(channels * bits / 8));
+        bytes.extend(u16_le(bits));
+        bytes.extend(b"data".iter().cloned());
+        bytes.extend(u32_le(data.len() as u32));
+        bytes.extend(data.iter().cloned());
+        bytes
+    }
+
+    #[test]
+    fn decodes_8_bit_mono() {
+        let samples = decode_wav(&wav(1, 1, 100, 8, &[128, 192, 64]), 100).unwrap();
+        assert_eq!(samples, vec![0.0, 0.0, 0.5, 0.5, -0.5, -0.5]);
+    }
+
+    #[test]
+    fn decodes_16_bit_stereo() {
+        // 0x4000 and 0xc000 are a half, either way
+        let samples = decode_wav(&wav(1, 2, 100, 16, &[0x00, 0x40, 0x00, 0xc0]), 100).unwrap();
+        assert_eq!(samples, vec![0.5, -0.5]);
+    }
+
+    #[test]
+    fn decodes_16_bit_mono_and_8_bit_stereo() {
+        let samples = decode_wav(&wav(1, 1, 100, 16, &[0x00, 0x40]), 100).unwrap();
+        assert_eq!(samples, vec![0.5, 0.5]);
+
+        let samples = decode_wav(&wav(1, 2, 100, 8, &[192, 64]), 100).unwrap();
+        assert_eq!(samples, vec![0.5, -0.5]);
+    }
+
+    #[test]
+    fn resamples_to_the_rate_of_the_device() {
+        let data = [128; 100];
+        assert_eq!(decode_wav(&wav(1, 1, 100, 8, &data), 200).unwrap().len(), 200 * 2);
+        assert_eq!(decode_wav(&wav(1, 1, 100, 8, &data), 50).unwrap().len(), 50 * 2);
+
+        // halfway between two samples, the sound is halfway between them
+        let samples = decode_wav(&wav(1, 1, 100, 8, &[128, 192]), 200).unwrap();
+        assert_eq!(&samples[..6], &[0.0, 0.0, 0.25, 0.25, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn rejects_compressed_files() {
+        assert!(decode_wav(&wav(3, 1, 100, 32, &[0; 4]), 100).is_err());
+        assert!(decode_wav(&wav(1, 1, 100, 24, &[0; 3]), 100).is_err());
+        assert!(decode_wav(&wav(1, 3, 100, 8, &[0; 3]), 100).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_and_malformed_files() {
+        let file = wav(1, 1, 100, 8, &[128, 192]);
+
+        // cut in the middle of the `fmt ` chunk, and before the `data` one
+        assert_eq!(decode_wav(&file[..24], 100), Err("truncated `fmt ` chunk".to_string()));
+        assert!(decode_wav(&file[..36], 100).is_err());
+        assert!(decode_wav(&file[..11], 100).is_err());
+        assert!(decode_wav(b"RIFF\0\0\0\0AVI LIST", 100).is_err());
+        assert!(decode_wav(&[], 100).is_err());
+    }
+
+    #[test]
+    fn the_oldest_sound_effect_makes_way() {
+        let mut mixer = mixer();
+        let sound = Sound { samples: Arc::new(vec![0.0; 20]) };
+        mixer.play_music(&sound);
+        for _ in 0..MAX_CHANNELS {
+            mixer.play(&sound);
+            let mut out = [0.0; 2];
+            mixer.callback(&mut out);
+        }
+        assert_eq!(mixer.voices.len(), MAX_CHANNELS + 1);
+
+        // the first sound effect played the longest, the music stays
+        mixer.play(&sound);
+        assert_eq!(mixer.voices.len(), MAX_CHANNELS + 1);
+        assert!(mixer.voices[0].music);
+        let oldest = mixer.voices.iter().filter(|voice| !voice.music).map(|voice| voice.frame).max();
+        assert_eq!(oldest, Some(MAX_CHANNELS - 1));
+    }
+
+    #[test]
+    fn volumes_multiply() {
+        let mut mixer = mixer();
+        mixer.master = 0.5;
+        mixer.sfx = 0.5;
+        mixer.music = 0.25;
+        mixer.play(&Sound { samples: Arc::new(vec![1.0, 1.0]) });
+        let mut out = [0.0; 2];
+        mixer.callback(&mut out);
+        assert_eq!(out, [0.25, 0.25]);
+
+        mixer.play_music(&Sound { samples: Arc::new(vec![1.0, 1.0]) });
+        mixer.callback(&mut out);
+        assert_eq!(out, [0.125, 0.125]);
+    }
+
+    #[test]
+    fn paused_music_is_silent_and_resumes_where_it_was() {
+        let mut mixer = mixer();
+        mixer.play_music(&Sound { samples: Arc::new(vec![0.1, 0.1, 0.2, 0.2, 0.3, 0.3]) });
+        let mut out = [0.0; 2];
+
+        mixer.callback(&mut out);
+        assert_eq!(out, [0.1, 0.1]);
+
+        mixer.music_paused = true;
+        mixer.callback(&mut out);
+        assert_eq!(out, [0.0, 0.0]);
+
+        mixer.music_paused = false;
+        mixer.callback(&mut out);
+        assert_eq!(out, [0.2, 0.2]);
+    }
+
+    #[test]
+    fn sound_effects_play_while_the_music_is_paused() {
+        let mut mixer = mixer();
+        mixer.play_music(&Sound { samples: Arc::new(vec![0.1, 0.1]) });
+        mixer.music_paused = true;
+        mixer.play(&Sound { samples: Arc::new(vec![0.5, 0.5]) });
+        let mut out = [0.0; 2];
+
+        mixer.callback(&mut out);
+        assert_eq!(out, [0.5, 0.5]);
+    }
+}
@@ -0,0 +1,178 @@
+use ::phi::Error;
+use ::phi::data::Rectangle;
+use ::sdl2::pixels::{Color, PixelFormatEnum};
+use ::sdl2::render::{BlendMode, Renderer, Texture};
+use ::sdl2::surface::Surface;
+use ::sdl2_image::LoadTexture;
+use ::std::path::Path;
+
+/// Everything a view needs from whatever it is drawing to.
+///
+/// Phi hands one of these out as `phi.renderer`, so the game code never
+/// touches a concrete SDL window.
+pub trait Backend {
+    /// Colors which are not fully opaque are blended with what was already
+    /// drawn.
+    fn set_draw_color(&mut self, color: Color);
+
+    /// Fills the whole output with the current draw color.
+    fn clear(&mut self);
+
+    fn fill_rect(&mut self, rect: Rectangle);
+
+    /// Copies the `src` region of `texture` to the `dest` region of the output.
+    fn copy(&mut self, texture: &Texture, src: Rectangle, dest: Rectangle);
+
+    /// Shows everything drawn since the last call.
+    fn present(&mut self);
+
+    fn output_size(&self) -> (f64, f64);
+
+    fn load_texture(&self, path: &str) -> Result<Texture, String>;
+
+    fn texture_from_surface(&self, surface: &Surface) -> Result<Texture, String>;
+
+    /// The draw calls which made up the last presented frame, if this
+    /// backend keeps track of them.
+    fn last_frame(&self) -> Option<&[DrawCall]> {
+        None
+    }
+}
+
+
+/// A single drawing operation, as recorded by the `HeadlessBackend`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCall {
+    Clear(Color),
+    FillRect(Color, Rectangle),
+    Copy { src: Rectangle, dest: Rectangle },
+}
+
+
+/// Draws to an SDL window.
+pub struct SdlBackend<'window> {
+    renderer: Renderer<'window>,
+}
+
+impl <'window> SdlBackend<'window> {
+    pub fn new(mut renderer: Renderer<'window>) -> SdlBackend<'window> {
+        renderer.set_blend_mode(BlendMode::Blend);
+        SdlBackend { renderer: renderer }
+    }
+}
+
+impl <'window> Backend for SdlBackend<'window> {
+    fn set_draw_color(&mut self, color: Color) {
+        self.renderer.set_draw_color(color);
+    }
+
+    fn clear(&mut self) {
+        self.renderer.clear();
+    }
+
+    fn fill_rect(&mut self, rect: Rectangle) {
+        // nothing is drawn for rectangles which SDL cannot represent
+        if let Ok(Some(rect)) = rect.to_sdl() {
+            self.renderer.fill_rect(rect);
+        }
+    }
+
+    fn copy(&mut self, texture: &Texture, src: Rectangle, dest: Rectangle) {
+        if let (Ok(Some(src)), Ok(Some(dest))) = (src.to_sdl(), dest.to_sdl()) {
+            self.renderer.copy(texture, Some(src), Some(dest));
+        }
+    }
+
+    fn present(&mut self) {
+        self.renderer.present();
+    }
+
+    fn output_size(&self) -> (f64, f64) {
+        let (w, h) = self.renderer.output_size().unwrap();
+        (w as f64, h as f64)
+    }
+
+    fn load_texture(&self, path: &str) -> Result<Texture, String> {
+        self.renderer.load_texture(Path::new(path))
+    }
+
+    fn texture_from_surface(&self, surface: &Surface) -> Result<Texture, String> {
+        self.renderer.create_texture_from_surface(surface)
+    }
+}
+
+
+/// Draws into an in-memory surface with SDL's software renderer, and
+/// records every draw call so that tests can inspect what a view did
+/// during a frame. No window is ever created.
+pub struct HeadlessBackend {
+    renderer: Renderer<'static>,
+    size: (f64, f64),
+    color: Color,
+    frame: Vec<DrawCall>,
+    last_frame: Vec<DrawCall>,
+}
+
+impl HeadlessBackend {
+    pub fn new(w: u32, h: u32) -> Result<HeadlessBackend, Error> {
+        let surface = try!(Surface::new(w, h, PixelFormatEnum::RGBA8888).map_err(Error::Sdl));
+        let mut renderer = try!(Renderer::from_surface(surface).map_err(Error::Sdl));
+        renderer.set_blend_mode(BlendMode::Blend);
+
+        Ok(HeadlessBackend {
+            renderer: renderer,
+            size: (w as f64, h as f64),
+            color: Color::RGB(0, 0, 0),
+            frame: vec![],
+            last_frame: vec![],
+        })
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn set_draw_color(&mut self, color: Color) {
+        self.color = color;
+        self.renderer.set_draw_color(color);
+    }
+
+    fn clear(&mut self) {
+        self.frame.push(DrawCall::Clear(self.color));
+        self.renderer.clear();
+    }
+
+    fn fill_rect(&mut self, rect: Rectangle) {
+        self.frame.push(DrawCall::FillRect(self.color, rect));
+        // nothing is drawn for rectangles which SDL cannot represent
+        if let Ok(Some(rect)) = rect.to_sdl() {
+            self.renderer.fill_rect(rect);
+        }
+    }
+
+    fn copy(&mut self, texture: &Texture, src: Rectangle, dest: Rectangle) {
+        self.frame.push(DrawCall::Copy { src: src, dest: dest });
+        if let (Ok(Some(src)), Ok(Some(dest))) = (src.to_sdl(), dest.to_sdl()) {
+            self.renderer.copy(texture, Some(src), Some(dest));
+        }
+    }
+
+    fn present(&mut self) {
+        self.last_frame = ::std::mem::replace(&mut self.frame, vec![]);
+        self.renderer.present();
+    }
+
+    fn output_size(&self) -> (f64, f64) {
+        self.size
+    }
+
+    fn load_texture(&self, path: &str) -> Result<Texture, String> {
+        self.renderer.load_texture(Path::new(path))
+    }
+
+    fn texture_from_surface(&self, surface: &Surface) -> Result<Texture, String> {
+        self.renderer.create_texture_from_surface(surface)
+    }
+
+    fn last_frame(&self) -> Option<&[DrawCall]> {
+        Some(&self.last_frame)
+    }
+}
@@ -1,4 +1,7 @@
+use ::phi::Error;
 use ::sdl2::rect::Rect as SdlRect;
+use ::std::cell::RefCell;
+use ::std::rc::Rc;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Rectangle {
@@ -9,13 +12,15 @@ pub struct Rectangle {
 }
 
 impl Rectangle {
-    /// Generates an SDL-Compatible Rect equivalent to `self`.
-    /// Panics if it could not be created, for example,
-    /// if a coordinate to a corner overflows an `i32`.
-    pub fn to_sdl(self) -> Option<SdlRect> {
-        assert!(self.w >= 0.0 && self.h >= 0.0);
+    /// Generates an SDL-Compatible Rect equivalent to `self`, or `None` if
+    /// it is empty. Fails if it could not be created, for example,
+    /// if its size is negative or a coordinate to a corner overflows an `i32`.
+    pub fn to_sdl(self) -> Result<Option<SdlRect>, Error> {
+        if self.w < 0.0 || self.h < 0.0 {
+            return Err(Error::Geometry(format!("{:?} has a negative size", self)));
+        }
         SdlRect::new(self.x as i32, self.y as i32, self.w as u32, self.h as u32)
-            .unwrap()
+            .map_err(|e| Error::Geometry(format!("{:?} cannot be drawn: {}", self, e)))
     }
 
     pub fn position_right(self) -> f64 {
@@ -46,7 +51,7 @@ impl Rectangle {
             else if self.position_right() >= parent.position_right() { parent.position_right() - self.w }
             else { self. x },
             if self.y < parent.y { parent.y }
-            else if self.position_bottom() >= parent.position_bottom() { parent.position_bottom() - self.y }
+            else if self.position_bottom() >= parent.position_bottom() { parent.position_bottom() - self.h }
             else { self.y }
         ))
     }
@@ -63,6 +68,12 @@ impl Rectangle {
         ymax >= self.y && ymax <= self.position_bottom()
     }
 
+    /// Whether the point `(x, y)` lies within the rectangle.
+    pub fn contains_point(&self, point: (f64, f64)) -> bool {
+        point.0 >= self.x && point.0 < self.position_right() &&
+        point.1 >= self.y && point.1 < self.position_bottom()
+    }
+
     pub fn overlaps(&self, other: Rectangle) -> bool {
         self.x < other.position_right() &&
         self.position_right() > other.x &&
@@ -82,20 +93,753 @@ impl Rectangle {
         (self.x + self.w / 2.0, self.y + self.h / 2.0)
     }
 
-}
+    /// The smallest rectangle holding both `self` and `other`.
+    pub fn union(self, other: Rectangle) -> Rectangle {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Rectangle {
+            x: x,
+            y: y,
+            w: self.position_right().max(other.position_right()) - x,
+            h: self.position_bottom().max(other.position_bottom()) - y,
+        }
+    }
 
-pub struct MaybeAlive<T> {
-    pub alive: bool,
-    pub value: T,
-}
+    /// When `self`, moving by `delta`, starts and stops overlapping `other`:
+    /// 0 is where it starts and 1 where it has moved all the way. Returns
+    /// `None` if they never overlap on the way.
+    pub fn sweep(self, delta: (f64, f64), other: Rectangle) -> Option<(f64, f64)> {
+        let (mut enter, mut exit) = (0.0f64, 1.0f64);
+
+        let axes = [(self.x, self.w, delta.0, other.x, other.w), (self.y, self.h, delta.1, other.y, other.h)];
+        for &(pos, size, d, other_pos, other_size) in &axes {
+            // they overlap on this axis while `pos` is strictly between these
+            let (low, high) = (other_pos - size, other_pos + other_size);
+            if d == 0.0 {
+                if pos <= low || pos >= high {
+                    return None;
+                }
+            } else {
+                let (t0, t1) = ((low - pos) / d, (high - pos) / d);
+                enter = enter.max(t0.min(t1));
+                exit = exit.min(t0.max(t1));
+            }
+        }
 
-impl <T> MaybeAlive<T> {
-    pub fn as_option(self) -> Option<T> {
-        if self.alive {
-            Some(self.value)
+        if enter < exit {
+            Some((enter, exit))
         } else {
             None
         }
     }
+
+    /// Linearly interpolates between `self` (at `alpha = 0`)
+    /// and `other` (at `alpha = 1`).
+    pub fn lerp(self, other: Rectangle, alpha: f64) -> Rectangle {
+        Rectangle {
+            x: self.x + (other.x - self.x) * alpha,
+            y: self.y + (other.y - self.y) * alpha,
+            w: self.w + (other.w - self.w) * alpha,
+            h: self.h + (other.h - self.h) * alpha,
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    /// The center of the circle.
+    pub x: f64,
+    pub y: f64,
+    pub r: f64,
+}
+
+impl Circle {
+    pub fn bounds(self) -> Rectangle {
+        Rectangle { x: self.x - self.r, y: self.y - self.r, w: self.r * 2.0, h: self.r * 2.0 }
+    }
+
+    pub fn contains_point(&self, point: (f64, f64)) -> bool {
+        let (dx, dy) = (point.0 - self.x, point.1 - self.y);
+        dx * dx + dy * dy < self.r * self.r
+    }
+
+    /// Whether the circle, moved by `(dx, dy)`, overlaps `rect`.
+    fn overlaps_rect(&self, dx: f64, dy: f64, rect: Rectangle) -> bool {
+        let (x, y) = (self.x + dx, self.y + dy);
+        let closest = (x.max(rect.x).min(rect.position_right()),
+                       y.max(rect.y).min(rect.position_bottom()));
+        Circle { x: x, y: y, r: self.r }.contains_point(closest)
+    }
+}
+
+
+/// A convex polygon.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    /// In order around the polygon, either way.
+    points: Vec<(f64, f64)>,
+    bounds: Rectangle,
+}
+
+impl Polygon {
+    /// Returns `None` unless `points` go around a convex polygon.
+    pub fn new(points: Vec<(f64, f64)>) -> Option<Polygon> {
+        if points.len() < 3 {
+            return None;
+        }
+
+        // every turn should be made in the same direction
+        let n = points.len();
+        let turns: Vec<f64> = (0..n)
+            .map(|i| cross(points[i], points[(i + 1) % n], points[(i + 2) % n]))
+            .collect();
+        if !(turns.iter().all(|&t| t >= 0.0) || turns.iter().all(|&t| t <= 0.0)) {
+            return None;
+        }
+
+        let (mut min, mut max) = (points[0], points[0]);
+        for &(x, y) in &points {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+
+        Some(Polygon {
+            points: points,
+            bounds: Rectangle { x: min.0, y: min.1, w: max.0 - min.0, h: max.1 - min.1 },
+        })
+    }
+
+    pub fn contains_point(&self, point: (f64, f64)) -> bool {
+        let n = self.points.len();
+        let sides: Vec<f64> = (0..n)
+            .map(|i| cross(self.points[i], self.points[(i + 1) % n], point))
+            .collect();
+        sides.iter().all(|&side| side > 0.0) || sides.iter().all(|&side| side < 0.0)
+    }
+
+    /// The points of the polygon, moved by `(dx, dy)`.
+    fn moved_by(&self, dx: f64, dy: f64) -> Vec<(f64, f64)> {
+        self.points.iter().map(|&(x, y)| (x + dx, y + dy)).collect()
+    }
+}
+
+
+/// Which pixels of an image are solid. Clones share their pixels, so that
+/// they all change when one of them is `replace`d, for example once the
+/// image was edited.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    pixels: Rc<RefCell<Pixels>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Pixels {
+    w: usize,
+    h: usize,
+    /// Row after row.
+    solid: Vec<bool>,
+    /// The smallest rectangle holding every solid pixel.
+    bounds: Rectangle,
+}
+
+impl Mask {
+    /// `solid` holds `w * h` pixels, row after row.
+    pub fn new(w: usize, h: usize, solid: Vec<bool>) -> Mask {
+        assert_eq!(solid.len(), w * h);
+
+        let mut bounds: Option<(usize, usize, usize, usize)> = None;
+        for y in 0..h {
+            for x in 0..w {
+                if solid[y * w + x] {
+                    bounds = Some(match bounds {
+                        Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                        None => (x, y, x, y),
+                    });
+                }
+            }
+        }
+
+        let pixels = Pixels {
+            w: w,
+            h: h,
+            solid: solid,
+            bounds: match bounds {
+                Some((x0, y0, x1, y1)) => Rectangle {
+                    x: x0 as f64,
+                    y: y0 as f64,
+                    w: (x1 - x0 + 1) as f64,
+                    h: (y1 - y0 + 1) as f64,
+                },
+                None => Rectangle::with_size(0.0, 0.0),
+            },
+        };
+        Mask { pixels: Rc::new(RefCell::new(pixels)) }
+    }
+
+    /// The smallest rectangle holding every solid pixel.
+    pub fn bounds(&self) -> Rectangle {
+        self.pixels.borrow().bounds
+    }
+
+    /// Whether the pixel under `point` is solid.
+    pub fn contains_point(&self, point: (f64, f64)) -> bool {
+        if point.0 < 0.0 || point.1 < 0.0 {
+            return false;
+        }
+        let pixels = self.pixels.borrow();
+        let (x, y) = (point.0 as usize, point.1 as usize);
+        x < pixels.w && y < pixels.h && pixels.solid[y * pixels.w + x]
+    }
+
+    /// Gives this mask, and every clone of it, the pixels of `other`.
+    pub fn replace(&self, other: Mask) {
+        if !Rc::ptr_eq(&self.pixels, &other.pixels) {
+            let pixels = match Rc::try_unwrap(other.pixels) {
+                Ok(pixels) => pixels.into_inner(),
+                Err(pixels) => {
+                    let pixels = pixels.borrow();
+                    Pixels { solid: pixels.solid.clone(), .. *pixels }
+                },
+            };
+            *self.pixels.borrow_mut() = pixels;
+        }
+    }
+
+    /// How many masks, this one included, share these pixels.
+    pub fn copies(&self) -> usize {
+        Rc::strong_count(&self.pixels)
+    }
+}
+
+
+/// The part of a sprite which collides with other things, relative to its
+/// top left corner.
+///
+/// Shapes are placed in the world by the position of their sprite, and
+/// tested against each other exactly, but for masks: they only collide
+/// where the center of one of their solid pixels falls within the other
+/// shape.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Rect(Rectangle),
+    Circle(Circle),
+    Polygon(Polygon),
+    Mask(Mask),
+}
+
+impl Shape {
+    /// The smallest rectangle holding the whole shape.
+    pub fn bounds(&self) -> Rectangle {
+        match *self {
+            Shape::Rect(rect) => rect,
+            Shape::Circle(circle) => circle.bounds(),
+            Shape::Polygon(ref polygon) => polygon.bounds,
+            Shape::Mask(ref mask) => mask.bounds(),
+        }
+    }
+
+    pub fn contains_point(&self, point: (f64, f64)) -> bool {
+        match *self {
+            Shape::Rect(rect) => rect.contains_point(point),
+            Shape::Circle(circle) => circle.contains_point(point),
+            Shape::Polygon(ref polygon) => polygon.contains_point(point),
+            Shape::Mask(ref mask) => mask.contains_point(point),
+        }
+    }
+
+    /// Whether the shape, with its sprite at `at`, overlaps `other`, with
+    /// its own sprite at `other_at`.
+    pub fn overlaps(&self, at: (f64, f64), other: &Shape, other_at: (f64, f64)) -> bool {
+        let bounds = self.bounds().moved_by(at.0, at.1);
+        let other_bounds = other.bounds().moved_by(other_at.0, other_at.1);
+        if !bounds.overlaps(other_bounds) {
+            return false;
+        }
+
+        // only the most complex shape of the two needs to be matched
+        if other.complexity() > self.complexity() {
+            return other.overlaps(other_at, self, at);
+        }
+
+        match (self, other) {
+            (&Shape::Mask(ref mask), _) => {
+                let area = Rectangle {
+                    x: bounds.x.max(other_bounds.x),
+                    y: bounds.y.max(other_bounds.y),
+                    w: bounds.position_right().min(other_bounds.position_right()) - bounds.x.max(other_bounds.x),
+                    h: bounds.position_bottom().min(other_bounds.position_bottom()) - bounds.y.max(other_bounds.y),
+                };
+
+                // the pixels of the mask, in the world, which may overlap
+                let (left, top) = ((area.x - at.0).floor(), (area.y - at.1).floor());
+                let (right, bottom) = ((area.position_right() - at.0).ceil(), (area.position_bottom() - at.1).ceil());
+
+                let mut y = top;
+                while y < bottom {
+                    let mut x = left;
+                    while x < right {
+                        let center = (x + 0.5, y + 0.5);
+                        let world = (center.0 + at.0, center.1 + at.1);
+                        if mask.contains_point(center) && area.contains_point(world) &&
+                           other.contains_point((world.0 - other_at.0, world.1 - other_at.1)) {
+                            return true;
+                        }
+                        x += 1.0;
+                    }
+                    y += 1.0;
+                }
+                false
+            },
+            (&Shape::Polygon(ref polygon), &Shape::Polygon(ref other)) => {
+                let points = polygon.moved_by(at.0, at.1);
+                let other = other.moved_by(other_at.0, other_at.1);
+                !separated(&points, &other) && !separated(&other, &points)
+            },
+            (&Shape::Polygon(ref polygon), &Shape::Circle(circle)) => {
+                let points = polygon.moved_by(at.0, at.1);
+                let circle = Circle { x: circle.x + other_at.0, y: circle.y + other_at.1, r: circle.r };
+                let n = points.len();
+                polygon.contains_point((circle.x - at.0, circle.y - at.1)) ||
+                    (0..n).any(|i| distance_to_segment((circle.x, circle.y), points[i], points[(i + 1) % n]) < circle.r)
+            },
+            (&Shape::Polygon(ref polygon), &Shape::Rect(_)) => {
+                let points = polygon.moved_by(at.0, at.1);
+                let corners = [
+                    (other_bounds.x, other_bounds.y),
+                    (other_bounds.position_right(), other_bounds.y),
+                    (other_bounds.position_right(), other_bounds.position_bottom()),
+                    (other_bounds.x, other_bounds.position_bottom()),
+                ];
+                !separated(&points, &corners) && !separated(&corners, &points)
+            },
+            (&Shape::Circle(circle), &Shape::Circle(other)) => {
+                let (dx, dy) = (circle.x + at.0 - other.x - other_at.0, circle.y + at.1 - other.y - other_at.1);
+                let r = circle.r + other.r;
+                dx * dx + dy * dy < r * r
+            },
+            (&Shape::Circle(circle), &Shape::Rect(_)) => circle.overlaps_rect(at.0, at.1, other_bounds),
+            // the bounds of rectangles are the rectangles themselves
+            _ => true,
+        }
+    }
+
+    /// When the shape, moving from `from` to `to`, first overlaps `other`,
+    /// placed at `other_at`: 0 if it does from the start, 1 if it only does
+    /// at the end. Returns `None` if they never overlap on the way.
+    ///
+    /// Two rectangles are swept exactly. Other shapes are tested at every
+    /// pixel of the way while their bounds overlap, so fast moving shapes
+    /// never go through thin ones.
+    pub fn sweep(&self, from: (f64, f64), to: (f64, f64), other: &Shape, other_at: (f64, f64)) -> Option<f64> {
+        let delta = (to.0 - from.0, to.1 - from.1);
+        let bounds = self.bounds().moved_by(from.0, from.1);
+        let (enter, exit) = match bounds.sweep(delta, other.bounds().moved_by(other_at.0, other_at.1)) {
+            Some(interval) => interval,
+            None => return None,
+        };
+
+        if let (&Shape::Rect(_), &Shape::Rect(_)) = (self, other) {
+            return Some(enter);
+        }
+
+        let steps = (delta.0.hypot(delta.1) * (exit - enter)).ceil().max(1.0) as usize;
+        (0..steps + 1)
+            .map(|step| enter + (exit - enter) * step as f64 / steps as f64)
+            .find(|&t| self.overlaps((from.0 + delta.0 * t, from.1 + delta.1 * t), other, other_at))
+    }
+
+    /// Orders the shapes, so that testing two of them is only written once.
+    fn complexity(&self) -> u8 {
+        match *self {
+            Shape::Rect(_) => 0,
+            Shape::Circle(_) => 1,
+            Shape::Polygon(_) => 2,
+            Shape::Mask(_) => 3,
+        }
+    }
+}
+
+/// Which way `c` turns from the line going from `a` to `b`.
+fn cross(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
 }
 
+/// Whether one of the edges of the convex polygon `a` separates it from the
+/// convex polygon `b`.
+fn separated(a: &[(f64, f64)], b: &[(f64, f64)]) -> bool {
+    let n = a.len();
+    (0..n).any(|i| {
+        let (p, q) = (a[i], a[(i + 1) % n]);
+        let axis = (p.1 - q.1, q.0 - p.0);
+        let project = |points: &[(f64, f64)]| points.iter()
+            .map(|&(x, y)| x * axis.0 + y * axis.1)
+            .fold((::std::f64::INFINITY, ::std::f64::NEG_INFINITY), |(min, max), d| (min.min(d), max.max(d)));
+
+        let (a_min, a_max) = project(a);
+        let (b_min, b_max) = project(b);
+        a_max <= b_min || b_max <= a_min
+    })
+}
+
+fn distance_to_segment(point: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length).max(0.0).min(1.0)
+    };
+    let (x, y) = (a.0 + dx * t - point.0, a.1 + dy * t - point.1);
+    (x * x + y * y).sqrt()
+}
+
+
+/// A broad phase for collisions: rectangles are put in the cells of a
+/// uniform grid which they cover, so that a rectangle only needs to be
+/// tested against those which share one of its cells, rather than against
+/// all of them.
+///
+/// The grid covers `bounds`. Rectangles reaching outside of it are put in
+/// the cells along its edges, so nothing is ever missed, only tested more
+/// often than needed.
+pub struct Grid {
+    cell_size: f64,
+    bounds: Rectangle,
+    cols: usize,
+    rows: usize,
+    /// The ids in every cell, row after row.
+    cells: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    pub fn new(bounds: Rectangle, cell_size: f64) -> Grid {
+        let mut grid = Grid {
+            cell_size: cell_size,
+            bounds: bounds,
+            cols: 0,
+            rows: 0,
+            cells: vec![],
+        };
+        grid.reset(bounds);
+        grid
+    }
+
+    /// Empties the grid, which now covers `bounds`. The memory of the cells
+    /// is kept, so that filling the grid again every frame is cheap.
+    pub fn reset(&mut self, bounds: Rectangle) {
+        self.bounds = bounds;
+        self.cols = ((bounds.w / self.cell_size).ceil() as usize).max(1);
+        self.rows = ((bounds.h / self.cell_size).ceil() as usize).max(1);
+
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+        self.cells.resize(self.cols * self.rows, vec![]);
+    }
+
+    /// The columns and rows of the cells covered by `rect`, inclusive.
+    fn span(&self, rect: Rectangle) -> ((usize, usize), (usize, usize)) {
+        let col = |x: f64| {
+            let col = ((x - self.bounds.x) / self.cell_size).floor();
+            col.max(0.0).min(self.cols as f64 - 1.0) as usize
+        };
+        let row = |y: f64| {
+            let row = ((y - self.bounds.y) / self.cell_size).floor();
+            row.max(0.0).min(self.rows as f64 - 1.0) as usize
+        };
+
+        ((col(rect.x), col(rect.position_right())), (row(rect.y), row(rect.position_bottom())))
+    }
+
+    pub fn insert(&mut self, id: usize, rect: Rectangle) {
+        let ((left, right), (top, bottom)) = self.span(rect);
+        for row in top..bottom + 1 {
+            for col in left..right + 1 {
+                self.cells[row * self.cols + col].push(id);
+            }
+        }
+    }
+
+    /// Replaces the contents of `ids` with the ids, in increasing order and
+    /// each only once, of the rectangles which may overlap `rect`.
+    pub fn query(&self, rect: Rectangle, ids: &mut Vec<usize>) {
+        ids.clear();
+        let ((left, right), (top, bottom)) = self.span(rect);
+        for row in top..bottom + 1 {
+            for col in left..right + 1 {
+                ids.extend_from_slice(&self.cells[row * self.cols + col]);
+            }
+        }
+        ids.sort();
+        ids.dedup();
+    }
+
+    /// Every pair of an index in `rects` and the id of a rectangle in the
+    /// grid which may overlap it. Only the benchmark needs them all at once.
+    #[cfg(any(test, feature = "bench"))]
+    pub fn pairs(&self, rects: &[Rectangle]) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        let mut ids = vec![];
+        for (i, &rect) in rects.iter().enumerate() {
+            self.query(rect, &mut ids);
+            pairs.extend(ids.iter().map(|&id| (i, id)));
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f64, y: f64, w: f64, h: f64) -> Rectangle {
+        Rectangle { x: x, y: y, w: w, h: h }
+    }
+
+    /// A grid of 2x2 cells of 64 pixels.
+    fn grid() -> Grid {
+        Grid::new(Rectangle::with_size(128.0, 128.0), 64.0)
+    }
+
+    fn query(grid: &Grid, rect: Rectangle) -> Vec<usize> {
+        let mut ids = vec![];
+        grid.query(rect, &mut ids);
+        ids
+    }
+
+    #[test]
+    fn grid_clamps_rects_outside_to_the_edge_cells() {
+        let mut grid = grid();
+        grid.insert(0, rect(-50.0, -50.0, 10.0, 10.0));
+        grid.insert(1, rect(500.0, 500.0, 10.0, 10.0));
+        grid.insert(2, rect(500.0, -50.0, 10.0, 10.0));
+
+        assert_eq!(query(&grid, rect(0.0, 0.0, 1.0, 1.0)), vec![0]);
+        assert_eq!(query(&grid, rect(127.0, 127.0, 1.0, 1.0)), vec![1]);
+        assert_eq!(query(&grid, rect(127.0, 0.0, 1.0, 1.0)), vec![2]);
+        assert_eq!(query(&grid, rect(-1000.0, -1000.0, 1.0, 1.0)), vec![0]);
+        assert_eq!(query(&grid, rect(0.0, 64.0, 1.0, 1.0)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn grid_finds_rects_in_every_cell_they_span() {
+        let mut grid = grid();
+        grid.insert(7, rect(60.0, 60.0, 10.0, 10.0));
+        grid.insert(3, rect(10.0, 10.0, 10.0, 10.0));
+
+        assert_eq!(query(&grid, rect(0.0, 0.0, 1.0, 1.0)), vec![3, 7]);
+        assert_eq!(query(&grid, rect(100.0, 0.0, 1.0, 1.0)), vec![7]);
+        assert_eq!(query(&grid, rect(0.0, 100.0, 1.0, 1.0)), vec![7]);
+        assert_eq!(query(&grid, rect(100.0, 100.0, 1.0, 1.0)), vec![7]);
+    }
+
+    #[test]
+    fn grid_reports_every_pair_once() {
+        let mut grid = grid();
+        grid.insert(7, rect(60.0, 60.0, 10.0, 10.0));
+        grid.insert(1, rect(100.0, 100.0, 10.0, 10.0));
+        grid.insert(0, rect(0.0, 0.0, 128.0, 128.0));
+
+        // the first rect and 7 share four cells, yet meet once
+        let pairs = grid.pairs(&[rect(0.0, 0.0, 128.0, 128.0), rect(0.0, 0.0, 1.0, 1.0)]);
+        assert_eq!(pairs, vec![(0, 0), (0, 1), (0, 7), (1, 0), (1, 7)]);
+    }
+
+    #[test]
+    fn grid_reset_empties_the_cells() {
+        let mut grid = grid();
+        grid.insert(0, rect(0.0, 0.0, 10.0, 10.0));
+        grid.reset(Rectangle::with_size(256.0, 64.0));
+        assert_eq!(query(&grid, rect(0.0, 0.0, 256.0, 64.0)), Vec::<usize>::new());
+
+        grid.insert(4, rect(200.0, 10.0, 10.0, 10.0));
+        assert_eq!(query(&grid, rect(250.0, 0.0, 1.0, 1.0)), vec![4]);
+        assert_eq!(query(&grid, rect(0.0, 0.0, 1.0, 1.0)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn replaced_masks_change_in_every_clone() {
+        let mask = Mask::new(2, 1, vec![true, false]);
+        let shape = Shape::Mask(mask.clone());
+        assert_eq!(mask.copies(), 2);
+
+        mask.replace(Mask::new(2, 1, vec![false, true]));
+        assert!(!shape.contains_point((0.5, 0.5)));
+        assert!(shape.contains_point((1.5, 0.5)));
+        assert_eq!(shape.bounds(), rect(1.0, 0.0, 1.0, 1.0));
+    }
+
+    fn triangle() -> Shape {
+        Shape::Polygon(Polygon::new(vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]).unwrap())
+    }
+
+    /// A 2x2 mask whose top left and bottom right pixels are solid.
+    fn diagonal() -> Shape {
+        Shape::Mask(Mask::new(2, 2, vec![true, false, false, true]))
+    }
+
+    /// Whether `a` at `at` and `b` at `b_at` overlap, checking that it does
+    /// not matter which one is asked.
+    fn overlap(a: &Shape, at: (f64, f64), b: &Shape, b_at: (f64, f64)) -> bool {
+        let overlaps = a.overlaps(at, b, b_at);
+        assert_eq!(overlaps, b.overlaps(b_at, a, at));
+        overlaps
+    }
+
+    #[test]
+    fn rects_overlap_unless_they_only_touch() {
+        let square = Shape::Rect(rect(0.0, 0.0, 10.0, 10.0));
+        assert!(overlap(&square, (0.0, 0.0), &square, (9.0, 9.0)));
+        assert!(!overlap(&square, (0.0, 0.0), &square, (10.0, 0.0)));
+        assert!(!overlap(&square, (0.0, 0.0), &square, (0.0, -10.0)));
+        assert!(overlap(&square, (20.0, 0.0), &Shape::Rect(rect(25.0, 5.0, 1.0, 1.0)), (0.0, 0.0)));
+    }
+
+    #[test]
+    fn circles_overlap_rects_and_circles() {
+        let circle = Shape::Circle(Circle { x: 5.0, y: 5.0, r: 5.0 });
+        let square = Shape::Rect(rect(0.0, 0.0, 10.0, 10.0));
+
+        assert!(overlap(&circle, (0.0, 0.0), &square, (9.0, 0.0)));
+        assert!(!overlap(&circle, (0.0, 0.0), &square, (10.0, 0.0)));
+        // the corners of the bounds of the circle are outside of it
+        assert!(!overlap(&circle, (0.0, 0.0), &square, (9.0, 9.0)));
+
+        assert!(overlap(&circle, (0.0, 0.0), &circle, (9.9, 0.0)));
+        assert!(!overlap(&circle, (0.0, 0.0), &circle, (10.0, 0.0)));
+        assert!(!overlap(&circle, (0.0, 0.0), &circle, (8.0, 8.0)));
+    }
+
+    #[test]
+    fn polygons_overlap_rects_circles_and_polygons() {
+        let triangle = triangle();
+
+        // beyond, across and along the long side
+        assert!(!overlap(&triangle, (0.0, 0.0), &Shape::Rect(rect(6.0, 6.0, 4.0, 4.0)), (0.0, 0.0)));
+        assert!(overlap(&triangle, (0.0, 0.0), &Shape::Rect(rect(4.0, 4.0, 4.0, 4.0)), (0.0, 0.0)));
+        assert!(!overlap(&triangle, (0.0, 0.0), &Shape::Rect(rect(5.0, 5.0, 4.0, 4.0)), (0.0, 0.0)));
+        assert!(overlap(&triangle, (1.0, 1.0), &Shape::Rect(rect(5.0, 5.0, 4.0, 4.0)), (0.0, 0.0)));
+
+        assert!(!overlap(&triangle, (0.0, 0.0), &Shape::Circle(Circle { x: 8.0, y: 8.0, r: 2.0 }), (0.0, 0.0)));
+        assert!(overlap(&triangle, (0.0, 0.0), &Shape::Circle(Circle { x: 8.0, y: 8.0, r: 5.0 }), (0.0, 0.0)));
+        assert!(overlap(&triangle, (0.0, 0.0), &Shape::Circle(Circle { x: 2.0, y: 2.0, r: 1.0 }), (0.0, 0.0)));
+        assert!(overlap(&triangle, (10.0, 10.0), &Shape::Circle(Circle { x: 2.0, y: 2.0, r: 1.0 }), (10.0, 10.0)));
+
+        // the other half of the square, sharing the long side
+        let other = Shape::Polygon(Polygon::new(vec![(10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]).unwrap());
+        assert!(!overlap(&triangle, (0.0, 0.0), &other, (0.0, 0.0)));
+        assert!(overlap(&triangle, (0.0, 0.0), &other, (-1.0, 0.0)));
+        assert!(overlap(&triangle, (0.0, 0.0), &triangle, (5.0, 0.0)));
+        assert!(!overlap(&triangle, (0.0, 0.0), &triangle, (10.0, 0.0)));
+    }
+
+    #[test]
+    fn polygons_must_be_convex() {
+        assert!(Polygon::new(vec![(0.0, 0.0), (1.0, 1.0)]).is_none());
+        // an arrow head
+        assert!(Polygon::new(vec![(0.0, 0.0), (10.0, 5.0), (0.0, 10.0), (3.0, 5.0)]).is_none());
+        // either way around
+        assert!(Polygon::new(vec![(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)]).is_some());
+        assert!(Polygon::new(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]).is_some());
+    }
+
+    #[test]
+    fn masks_only_overlap_with_their_solid_pixels() {
+        let mask = diagonal();
+
+        // the top right pixel is within the bounds of the mask, but empty
+        assert!(!overlap(&mask, (0.0, 0.0), &Shape::Rect(rect(1.0, 0.0, 1.0, 1.0)), (0.0, 0.0)));
+        assert!(overlap(&mask, (0.0, 0.0), &Shape::Rect(rect(1.0, 1.0, 1.0, 1.0)), (0.0, 0.0)));
+        assert!(!overlap(&mask, (0.0, 0.0), &Shape::Rect(rect(2.0, 1.0, 1.0, 1.0)), (0.0, 0.0)));
+
+        let dot = |x, y| Shape::Circle(Circle { x: x, y: y, r: 0.4 });
+        assert!(overlap(&mask, (0.0, 0.0), &dot(0.5, 0.5), (0.0, 0.0)));
+        assert!(!overlap(&mask, (0.0, 0.0), &dot(1.5, 0.5), (0.0, 0.0)));
+
+        assert!(overlap(&mask, (0.0, 0.0), &triangle(), (-4.0, -4.0)));
+        assert!(!overlap(&mask, (0.0, 0.0), &triangle(), (-5.0, -5.0)));
+
+        assert!(overlap(&mask, (0.0, 0.0), &mask, (0.0, 0.0)));
+        assert!(!overlap(&mask, (0.0, 0.0), &mask, (1.0, 0.0)));
+        assert!(overlap(&mask, (0.0, 0.0), &mask, (1.0, 1.0)));
+    }
+
+    #[test]
+    fn masks_are_placed_by_their_offset() {
+        let mask = diagonal();
+        assert!(!overlap(&mask, (100.0, 50.0), &Shape::Rect(rect(101.0, 50.0, 1.0, 1.0)), (0.0, 0.0)));
+        assert!(overlap(&mask, (100.0, 50.0), &Shape::Rect(rect(101.0, 51.0, 1.0, 1.0)), (0.0, 0.0)));
+        assert!(overlap(&mask, (100.0, 50.0), &Shape::Rect(rect(0.0, 0.0, 1.0, 1.0)), (101.0, 51.0)));
+        // half a pixel off, only the centers of the pixels count
+        assert!(!overlap(&mask, (100.5, 50.0), &Shape::Rect(rect(101.0, 51.0, 0.9, 1.0)), (0.0, 0.0)));
+        assert!(overlap(&mask, (100.5, 50.0), &Shape::Rect(rect(101.0, 51.0, 1.1, 1.0)), (0.0, 0.0)));
+    }
+
+    #[test]
+    fn rects_sweep_from_entering_to_leaving() {
+        let moving = rect(0.0, 0.0, 10.0, 10.0);
+        let other = rect(50.0, 0.0, 10.0, 10.0);
+
+        assert_eq!(moving.sweep((100.0, 0.0), other), Some((0.4, 0.6)));
+        assert_eq!(moving.sweep((-100.0, 0.0), rect(-50.0, 5.0, 10.0, 10.0)), Some((0.4, 0.6)));
+        // the latest of the axes to enter and the earliest to leave
+        assert_eq!(moving.sweep((100.0, 100.0), rect(50.0, 20.0, 10.0, 40.0)), Some((0.4, 0.6)));
+        // too far to be reached
+        assert_eq!(moving.sweep((30.0, 0.0), other), None);
+        assert_eq!(moving.sweep((40.0, 0.0), other), None);
+    }
+
+    #[test]
+    fn rects_sweep_without_moving() {
+        let square = rect(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(square.sweep((0.0, 0.0), rect(5.0, 5.0, 10.0, 10.0)), Some((0.0, 1.0)));
+        assert_eq!(square.sweep((0.0, 0.0), rect(10.0, 0.0, 10.0, 10.0)), None);
+    }
+
+    #[test]
+    fn rects_sweep_from_where_they_start_overlapping() {
+        let moving = rect(45.0, 0.0, 10.0, 10.0);
+        assert_eq!(moving.sweep((100.0, 0.0), rect(50.0, 0.0, 10.0, 10.0)), Some((0.0, 0.15)));
+    }
+
+    #[test]
+    fn rects_moving_alongside_miss() {
+        let moving = rect(0.0, 20.0, 10.0, 10.0);
+        assert_eq!(moving.sweep((100.0, 0.0), rect(50.0, 0.0, 10.0, 10.0)), None);
+        // touching all the way does not count either
+        assert_eq!(moving.sweep((100.0, 0.0), rect(50.0, 10.0, 10.0, 10.0)), None);
+        assert_eq!(moving.sweep((0.0, -100.0), rect(10.0, -50.0, 10.0, 10.0)), None);
+    }
+
+    #[test]
+    fn shapes_sweep_to_when_they_first_overlap() {
+        let square = Shape::Rect(rect(0.0, 0.0, 10.0, 10.0));
+        assert_eq!(square.sweep((0.0, 0.0), (100.0, 0.0), &square, (50.0, 0.0)), Some(0.4));
+        assert_eq!(square.sweep((55.0, 0.0), (100.0, 0.0), &square, (50.0, 0.0)), Some(0.0));
+        assert_eq!(square.sweep((0.0, 20.0), (100.0, 20.0), &square, (50.0, 0.0)), None);
+
+        // the triangle does not reach the corner of its bounds
+        let corner = Shape::Rect(rect(8.0, 8.0, 2.0, 2.0));
+        assert_eq!(triangle().sweep((0.0, 0.0), (0.0, 0.0), &corner, (0.0, 0.0)), None);
+        assert_eq!(triangle().sweep((0.0, 0.0), (0.0, 0.0), &corner, (-4.0, -4.0)), Some(0.0));
+        assert_eq!(triangle().sweep((0.0, 0.0), (4.0, 4.0), &corner, (0.0, 0.0)).map(|t| t > 0.0), Some(true));
+    }
+
+    #[test]
+    fn shapes_do_not_go_through_thin_ones() {
+        // a bullet moving 100 pixels in a tick, across a wall 1 pixel thick
+        let bullet = Shape::Circle(Circle { x: 2.0, y: 5.0, r: 2.0 });
+        let wall = Shape::Polygon(Polygon::new(vec![(50.0, 0.0), (51.0, 0.0), (51.0, 10.0), (50.0, 10.0)]).unwrap());
+        assert!(!bullet.overlaps((0.0, 0.0), &wall, (0.0, 0.0)));
+        assert!(!bullet.overlaps((100.0, 0.0), &wall, (0.0, 0.0)));
+
+        let time = bullet.sweep((0.0, 0.0), (100.0, 0.0), &wall, (0.0, 0.0)).unwrap();
+        assert!(time > 0.45 && time < 0.48, "hit at {}", time);
+
+        let column = Shape::Mask(Mask::new(1, 10, vec![true; 10]));
+        let bullet = Shape::Rect(rect(0.0, 4.0, 8.0, 4.0));
+        let time = bullet.sweep((0.0, 0.0), (100.0, 0.0), &column, (50.0, 0.0)).unwrap();
+        assert!(time > 0.42 && time < 0.44, "hit at {}", time);
+        assert_eq!(bullet.sweep((0.0, 20.0), (100.0, 20.0), &column, (50.0, 0.0)), None);
+    }
+}
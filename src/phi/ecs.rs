@@ -0,0 +1,952 @@
+use ::phi::Phi;
+use ::phi::data::{Grid, Rectangle, Shape};
+use ::phi::gfx::{AnimatedSprite, CopySprite, Sprite};
+use ::phi::pool::{self, Handle, Pool};
+use ::sdl2::pixels::Color;
+use ::std::rc::Rc;
+
+/// The size of the cells of the grid used to find what collides.
+const GRID_CELL: f64 = 64.0;
+
+
+/// A handle to something in a `World`. Handles to destroyed entities stay
+/// harmless: their slot may be reused, but never under the same generation.
+pub type Entity = Handle;
+
+/// Hands out entities, reusing the slots of the destroyed ones.
+pub struct Entities {
+    pool: Pool<()>,
+}
+
+impl Entities {
+    pub fn new() -> Entities {
+        Entities { pool: Pool::new() }
+    }
+
+    pub fn create(&mut self) -> Entity {
+        self.pool.insert(())
+    }
+
+    /// Returns `false` if `entity` was already destroyed.
+    pub fn destroy(&mut self, entity: Entity) -> bool {
+        self.pool.remove(entity).is_some()
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.pool.contains(entity)
+    }
+}
+
+
+/// One kind of component, for the entities which have it. The components
+/// are packed in a pool, and found through the index of their entity.
+pub struct Components<T> {
+    /// Where the component of every entity is in `pool`, by the index of
+    /// the entity.
+    handles: Vec<Option<Handle>>,
+    pool: Pool<(Entity, T)>,
+}
+
+impl<T> Components<T> {
+    pub fn new() -> Components<T> {
+        Components {
+            handles: vec![],
+            pool: Pool::new(),
+        }
+    }
+
+    /// How many entities have the component.
+    pub fn len(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Gives the component to `entity`, replacing the one it had.
+    pub fn insert(&mut self, entity: Entity, value: T) {
+        let index = entity.index();
+        while self.handles.len() <= index {
+            self.handles.push(None);
+        }
+
+        // whatever was left in the slot by a destroyed entity is reused
+        if let Some(handle) = self.handles[index] {
+            if let Some(slot) = self.pool.get_mut(handle) {
+                *slot = (entity, value);
+                return;
+            }
+        }
+        self.handles[index] = Some(self.pool.insert((entity, value)));
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        match self.handle(entity) {
+            Some(handle) => {
+                self.handles[entity.index()] = None;
+                self.pool.remove(handle).map(|(_, value)| value)
+            },
+            None => None,
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.handle(entity)
+            .and_then(|handle| self.pool.get(handle))
+            .map(|&(_, ref value)| value)
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.handle(entity) {
+            Some(handle) => self.pool.get_mut(handle).map(|&mut (_, ref mut value)| value),
+            None => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.handles.clear();
+        self.pool.clear();
+    }
+
+    /// Drops the components of the entities which were destroyed, for the
+    /// components which are not stored in a `World`. `dead` is only used to
+    /// gather those entities, so that it can be reused.
+    pub fn retain_alive(&mut self, entities: &Entities, dead: &mut Vec<Entity>) {
+        dead.clear();
+        dead.extend(self.iter()
+            .map(|(entity, _)| entity)
+            .filter(|&entity| !entities.is_alive(entity)));
+        for &entity in dead.iter() {
+            self.remove(entity);
+        }
+    }
+
+    /// Every entity which has the component, in the order in which the
+    /// components are packed.
+    pub fn iter(&self) -> Iter<T> {
+        Iter { values: self.pool.iter() }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<T> {
+        IterMut { values: self.pool.iter_mut() }
+    }
+
+    /// The handle in `pool` of the component of `entity` itself, rather
+    /// than of an entity which used its slot before.
+    fn handle(&self, entity: Entity) -> Option<Handle> {
+        match self.handles.get(entity.index()) {
+            Some(&Some(handle)) if self.pool.get(handle).map_or(false, |&(owner, _)| owner == entity) => {
+                Some(handle)
+            },
+            _ => None,
+        }
+    }
+}
+
+pub struct Iter<'a, T: 'a> {
+    values: pool::Iter<'a, (Entity, T)>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (Entity, &'a T);
+
+    fn next(&mut self) -> Option<(Entity, &'a T)> {
+        self.values.next().map(|(_, &(entity, ref value))| (entity, value))
+    }
+}
+
+pub struct IterMut<'a, T: 'a> {
+    values: pool::IterMut<'a, (Entity, T)>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = (Entity, &'a mut T);
+
+    fn next(&mut self) -> Option<(Entity, &'a mut T)> {
+        self.values.next().map(|(_, &mut (entity, ref mut value))| (entity, value))
+    }
+}
+
+
+/// Where an entity is, and where it was at the start of the tick, to draw
+/// it in between. The movement system keeps `prev` up to date for the
+/// entities with a velocity; whatever moves the others has to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub prev: Rectangle,
+    pub rect: Rectangle,
+}
+
+impl Transform {
+    /// An entity which stands still at `rect`.
+    pub fn new(rect: Rectangle) -> Transform {
+        Transform { prev: rect, rect: rect }
+    }
+
+    /// Where the entity is drawn, `alpha` of the way through the tick.
+    pub fn at(&self, alpha: f64) -> Rectangle {
+        self.prev.lerp(self.rect, alpha)
+    }
+
+    /// Where the shape of the entity is placed.
+    pub fn position(&self) -> (f64, f64) {
+        (self.rect.x, self.rect.y)
+    }
+}
+
+/// In pixels per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Velocity {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone)]
+pub enum Visual {
+    Sprite(Sprite),
+    Animation(AnimatedSprite),
+    /// The whole rectangle of the entity, filled with a color.
+    Fill(Color),
+}
+
+/// How an entity is drawn. The entities on lower layers are drawn first.
+#[derive(Clone)]
+pub struct Look {
+    pub visual: Visual,
+    pub layer: i32,
+    pub visible: bool,
+}
+
+impl Look {
+    pub fn new(visual: Visual, layer: i32) -> Look {
+        Look { visual: visual, layer: layer, visible: true }
+    }
+}
+
+/// The part of an entity which collides with others, relative to its
+/// transform. There is a shape for every frame of its animation, or
+/// `frame` picks one for entities which are not animated. Without any
+/// shape, the entity collides with nothing.
+#[derive(Clone)]
+pub struct Hitbox {
+    shapes: Rc<Vec<Shape>>,
+    pub frame: usize,
+}
+
+impl Hitbox {
+    pub fn new(shape: Shape) -> Hitbox {
+        Hitbox::frames(Rc::new(vec![shape]))
+    }
+
+    pub fn frames(shapes: Rc<Vec<Shape>>) -> Hitbox {
+        Hitbox { shapes: shapes, frame: 0 }
+    }
+
+    fn shape(&self, frame: usize) -> Option<&Shape> {
+        if self.shapes.is_empty() {
+            None
+        } else {
+            Some(&self.shapes[frame % self.shapes.len()])
+        }
+    }
+}
+
+/// How many hits an entity takes before it is destroyed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health(pub u32);
+
+/// How long, in seconds, until the entity is destroyed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lifetime(pub f64);
+
+/// Which side an entity is on. Only the entities of sides which hurt each
+/// other collide.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Team {
+    Player,
+    Enemy,
+    /// Hurts the player, but not the enemies.
+    Neutral,
+}
+
+impl Team {
+    pub fn hurts(self, other: Team) -> bool {
+        match (self, other) {
+            (Team::Player, Team::Player) => false,
+            (Team::Player, _) | (_, Team::Player) => true,
+            _ => false,
+        }
+    }
+}
+
+/// How an entity moves up and down as time goes by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Curve {
+    Sine { amplitude: f64, angular_vel: f64 },
+    /// Dips by `a` times 4/27 at first, is back where it started after `b`
+    /// seconds, and then goes off the other way faster and faster.
+    Cubic { a: f64, b: f64 },
+}
+
+impl Curve {
+    /// How far below where it started the entity is after `time` seconds.
+    pub fn dy(self, time: f64) -> f64 {
+        match self {
+            Curve::Sine { amplitude, angular_vel } => amplitude * (angular_vel * time).sin(),
+            Curve::Cubic { a, b } => a * ((time / b).powi(3) - (time / b).powi(2)),
+        }
+    }
+}
+
+/// Moves an entity up and down around `origin_y`, along `curve`. Its
+/// velocity only moves it sideways, and keeps `prev` up to date.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Path {
+    pub origin_y: f64,
+    pub curve: Curve,
+    /// How long the entity has followed the path.
+    pub time: f64,
+}
+
+impl Path {
+    pub fn new(origin_y: f64, curve: Curve) -> Path {
+        Path { origin_y: origin_y, curve: curve, time: 0.0 }
+    }
+}
+
+/// Makes an entity charge at the target at `speed` once it is left of
+/// `at_x`. Without a target, it charges the way it was going.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Charge {
+    pub at_x: f64,
+    pub speed: f64,
+}
+
+/// Makes an entity flying left stop at `at_x`. It flies on at `speed`
+/// once it has had the component for `left` seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Halt {
+    pub at_x: f64,
+    pub left: f64,
+    pub speed: f64,
+}
+
+/// Which way the bullets of a gun go.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aim {
+    /// Always the same way, a unit vector.
+    Fixed(f64, f64),
+    /// At the target, or the same way as `Fixed` without one.
+    Target(f64, f64),
+    /// `bullets` of them all around, the ring turning by `spin` radians for
+    /// every second the gun has had.
+    Ring { bullets: usize, spin: f64 },
+}
+
+/// Fires bullets every `interval` seconds, `burst` of them at a time,
+/// `burst_delay` apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gun {
+    pub aim: Aim,
+    /// Where the bullets leave from, relative to the transform.
+    pub muzzle: (f64, f64),
+    pub interval: f64,
+    pub burst: u32,
+    pub burst_delay: f64,
+    /// How long until it fires again.
+    pub fire_in: f64,
+    /// The shots left in the current burst.
+    shots_left: u32,
+    /// How long the entity has had the gun.
+    time: f64,
+}
+
+impl Gun {
+    /// A gun which fires one bullet at a time, first in `fire_in` seconds.
+    pub fn new(aim: Aim, muzzle: (f64, f64), interval: f64, fire_in: f64) -> Gun {
+        Gun {
+            aim: aim,
+            muzzle: muzzle,
+            interval: interval,
+            burst: 1,
+            burst_delay: 0.0,
+            fire_in: fire_in,
+            shots_left: 0,
+            time: 0.0,
+        }
+    }
+
+    /// The same gun, firing `burst` bullets at a time, `delay` apart.
+    pub fn bursts(self, burst: u32, delay: f64) -> Gun {
+        Gun { burst: burst, burst_delay: delay, .. self }
+    }
+}
+
+/// What destroying an entity with `World::hurt` is worth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Points(pub u64);
+
+/// An entity which `World::hurt` destroyed, as it was hit by `by`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Death {
+    pub entity: Entity,
+    pub by: Entity,
+    /// Where the entity was.
+    pub center: (f64, f64),
+    pub points: u64,
+}
+
+/// Two entities whose hitboxes touched during a tick, `time` of the way
+/// through it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    pub a: Entity,
+    pub b: Entity,
+    pub time: f64,
+}
+
+
+/// Every entity of a game, with the components which phi knows how to
+/// handle: its systems move and steer them, let them fire, animate them,
+/// find what collides, hurt them and draw them. A game keeps the
+/// components only it knows about in `Components` of its own, next to the
+/// world.
+pub struct World {
+    pub entities: Entities,
+    pub transforms: Components<Transform>,
+    pub velocities: Components<Velocity>,
+    pub looks: Components<Look>,
+    pub hitboxes: Components<Hitbox>,
+    pub health: Components<Health>,
+    pub lifetimes: Components<Lifetime>,
+    pub teams: Components<Team>,
+    pub paths: Components<Path>,
+    pub charges: Components<Charge>,
+    pub halts: Components<Halt>,
+    pub guns: Components<Gun>,
+    pub points: Components<Points>,
+    /// The entities destroyed by `hurt`, until the game takes them.
+    pub deaths: Vec<Death>,
+    grid: Grid,
+    /// Everything which collides, with the bounds of the way it went during
+    /// the tick, kept between ticks so that finding collisions allocates
+    /// nothing.
+    bodies: Vec<(Entity, Team, Rectangle)>,
+    /// The bodies which may collide with one of them, reused between them.
+    candidates: Vec<usize>,
+    /// The destroyed entities found by `retain_alive`, reused between calls.
+    dead: Vec<Entity>,
+}
+
+impl World {
+    pub fn new() -> World {
+        World {
+            entities: Entities::new(),
+            transforms: Components::new(),
+            velocities: Components::new(),
+            looks: Components::new(),
+            hitboxes: Components::new(),
+            health: Components::new(),
+            lifetimes: Components::new(),
+            teams: Components::new(),
+            paths: Components::new(),
+            charges: Components::new(),
+            halts: Components::new(),
+            guns: Components::new(),
+            points: Components::new(),
+            deaths: vec![],
+            grid: Grid::new(Rectangle::with_size(0.0, 0.0), GRID_CELL),
+            bodies: vec![],
+            candidates: vec![],
+            dead: vec![],
+        }
+    }
+
+    pub fn create(&mut self) -> Entity {
+        self.entities.create()
+    }
+
+    /// Destroys `entity` along with its components.
+    pub fn destroy(&mut self, entity: Entity) {
+        if self.entities.destroy(entity) {
+            self.transforms.remove(entity);
+            self.velocities.remove(entity);
+            self.looks.remove(entity);
+            self.hitboxes.remove(entity);
+            self.health.remove(entity);
+            self.lifetimes.remove(entity);
+            self.teams.remove(entity);
+            self.paths.remove(entity);
+            self.charges.remove(entity);
+            self.halts.remove(entity);
+            self.guns.remove(entity);
+            self.points.remove(entity);
+        }
+    }
+
+    /// Drops the components, kept outside of the world, of the entities
+    /// which were destroyed.
+    pub fn retain_alive<T>(&mut self, components: &mut Components<T>) {
+        components.retain_alive(&self.entities, &mut self.dead);
+    }
+
+    /// The shape of `entity` for the frame it shows, relative to its
+    /// transform.
+    pub fn shape(&self, entity: Entity) -> Option<&Shape> {
+        self.hitboxes.get(entity).and_then(|hitbox| {
+            match self.looks.get(entity).map(|look| &look.visual) {
+                Some(&Visual::Animation(ref animation)) => hitbox.shape(animation.current_frame()),
+                _ => hitbox.shape(hitbox.frame),
+            }
+        })
+    }
+
+    /// When `entity` first touched `other`, placed at `at`, on its way
+    /// during the tick.
+    pub fn sweep(&self, entity: Entity, other: &Shape, at: (f64, f64)) -> Option<f64> {
+        match (self.transforms.get(entity), self.shape(entity)) {
+            (Some(transform), Some(shape)) => {
+                shape.sweep((transform.prev.x, transform.prev.y), transform.position(), other, at)
+            },
+            _ => None,
+        }
+    }
+
+    /// The movement system: moves the entities which have a velocity. Those
+    /// which are out of `area` and moving away from it are destroyed.
+    pub fn move_all(&mut self, dt: f64, area: Rectangle) {
+        let mut gone = vec![];
+        for (entity, velocity) in self.velocities.iter() {
+            if let Some(transform) = self.transforms.get_mut(entity) {
+                transform.prev = transform.rect;
+                transform.rect = transform.rect.moved_by(velocity.x * dt, velocity.y * dt);
+
+                let rect = transform.rect;
+                if (rect.position_right() <= area.x && velocity.x <= 0.0) ||
+                   (rect.x >= area.position_right() && velocity.x >= 0.0) ||
+                   (rect.position_bottom() <= area.y && velocity.y <= 0.0) ||
+                   (rect.y >= area.position_bottom() && velocity.y >= 0.0) {
+                    gone.push(entity);
+                }
+            }
+        }
+
+        for entity in gone {
+            self.destroy(entity);
+        }
+    }
+
+    /// The path system: moves the entities which follow a path up and down,
+    /// after the movement system moved them sideways.
+    pub fn follow_paths(&mut self, dt: f64) {
+        for (entity, path) in self.paths.iter_mut() {
+            path.time += dt;
+            if let Some(transform) = self.transforms.get_mut(entity) {
+                transform.rect.y = path.origin_y + path.curve.dy(path.time);
+            }
+        }
+    }
+
+    /// The steering system: turns the entities which charge towards
+    /// `target` once they reach where they charge, before the movement
+    /// system moves them.
+    pub fn charge(&mut self, target: Option<(f64, f64)>) {
+        let mut charging = vec![];
+        for (entity, charge) in self.charges.iter() {
+            let (transform, velocity) = match (self.transforms.get(entity), self.velocities.get_mut(entity)) {
+                (Some(transform), Some(velocity)) => (transform, velocity),
+                _ => continue,
+            };
+            if transform.rect.x >= charge.at_x {
+                continue;
+            }
+
+            let (x, y) = transform.rect.center();
+            let heading = target.and_then(|target| unit(target.0 - x, target.1 - y))
+                .or_else(|| unit(velocity.x, velocity.y))
+                .unwrap_or((-1.0, 0.0));
+            *velocity = Velocity { x: heading.0 * charge.speed, y: heading.1 * charge.speed };
+            charging.push(entity);
+        }
+
+        for entity in charging {
+            self.charges.remove(entity);
+        }
+    }
+
+    /// The halting system: stops the entities which halt where they halt,
+    /// after the movement system moved them, and sends them on their way
+    /// once their time is up.
+    pub fn halt(&mut self, dt: f64) {
+        for (entity, halt) in self.halts.iter_mut() {
+            halt.left -= dt;
+            let (transform, velocity) = match (self.transforms.get_mut(entity), self.velocities.get_mut(entity)) {
+                (Some(transform), Some(velocity)) => (transform, velocity),
+                _ => continue,
+            };
+
+            if halt.left > 0.0 && transform.rect.x <= halt.at_x {
+                transform.rect.x = halt.at_x;
+                velocity.x = 0.0;
+            } else {
+                velocity.x = -halt.speed;
+            }
+        }
+    }
+
+    /// The firing system: lets the guns which are due fire, once they are
+    /// in `area`, aiming at `target` if there is one. Returns where every
+    /// bullet leaves from, and which way it goes.
+    pub fn fire(&mut self, dt: f64, area: Rectangle, target: Option<(f64, f64)>) -> Vec<((f64, f64), (f64, f64))> {
+        let mut fired = vec![];
+        for (entity, gun) in self.guns.iter_mut() {
+            gun.time += dt;
+            gun.fire_in -= dt;
+            if gun.fire_in > 0.0 {
+                continue;
+            }
+
+            if gun.shots_left == 0 {
+                gun.shots_left = gun.burst;
+            }
+            gun.shots_left = gun.shots_left.saturating_sub(1);
+            gun.fire_in += if gun.shots_left > 0 { gun.burst_delay } else { gun.interval };
+
+            let muzzle = match self.transforms.get(entity) {
+                Some(transform) => (transform.rect.x + gun.muzzle.0, transform.rect.y + gun.muzzle.1),
+                None => continue,
+            };
+            if !area.contains_point(muzzle) {
+                continue;
+            }
+
+            match gun.aim {
+                Aim::Fixed(x, y) => fired.push((muzzle, (x, y))),
+                Aim::Target(x, y) => {
+                    let dir = target.and_then(|target| unit(target.0 - muzzle.0, target.1 - muzzle.1));
+                    fired.push((muzzle, dir.unwrap_or((x, y))));
+                },
+                Aim::Ring { bullets, spin } => {
+                    for i in 0..bullets {
+                        let angle = gun.time * spin + ::std::f64::consts::PI * 2.0 * i as f64 / bullets as f64;
+                        fired.push((muzzle, (angle.cos(), angle.sin())));
+                    }
+                },
+            }
+        }
+        fired
+    }
+
+    /// The damage system: takes `damage` from the health of `entity`, hit
+    /// by `by`. Once it has none left, it is destroyed and its death kept in
+    /// `deaths`. Returns `false` if it cannot be hurt, having no health or
+    /// being destroyed already.
+    pub fn hurt(&mut self, entity: Entity, damage: u32, by: Entity) -> bool {
+        let left = match self.health.get_mut(entity) {
+            Some(health) => {
+                health.0 = health.0.saturating_sub(damage);
+                health.0
+            },
+            None => return false,
+        };
+
+        if left == 0 {
+            self.deaths.push(Death {
+                entity: entity,
+                by: by,
+                center: self.transforms.get(entity).map_or((0.0, 0.0), |transform| transform.rect.center()),
+                points: self.points.get(entity).map_or(0, |points| points.0),
+            });
+            self.destroy(entity);
+        }
+        true
+    }
+
+    /// The animation system: plays the animations, and destroys the entities
+    /// whose lifetime is over.
+    pub fn animate(&mut self, dt: f64) {
+        for (_, look) in self.looks.iter_mut() {
+            if let Visual::Animation(ref mut animation) = look.visual {
+                animation.add_time(dt);
+            }
+        }
+
+        let mut expired = vec![];
+        for (entity, lifetime) in self.lifetimes.iter_mut() {
+            lifetime.0 -= dt;
+            if lifetime.0 <= 0.0 {
+                expired.push(entity);
+            }
+        }
+
+        for entity in expired {
+            self.destroy(entity);
+        }
+    }
+
+    /// The collision system: every pair of entities of teams which hurt each
+    /// other, whose hitboxes touched on their way during the tick. `area` is
+    /// where most of them are.
+    pub fn collisions(&mut self, area: Rectangle) -> Vec<Contact> {
+        let mut bodies = ::std::mem::replace(&mut self.bodies, vec![]);
+        bodies.clear();
+        for (entity, &team) in self.teams.iter() {
+            if let (Some(transform), Some(shape)) = (self.transforms.get(entity), self.shape(entity)) {
+                let bounds = shape.bounds();
+                let way = bounds.moved_by(transform.prev.x, transform.prev.y)
+                    .union(bounds.moved_by(transform.rect.x, transform.rect.y));
+                bodies.push((entity, team, way));
+            }
+        }
+
+        self.grid.reset(area);
+        for (i, &(_, _, way)) in bodies.iter().enumerate() {
+            self.grid.insert(i, way);
+        }
+
+        let mut contacts = vec![];
+        for (i, &(a, team, way)) in bodies.iter().enumerate() {
+            self.grid.query(way, &mut self.candidates);
+            for &j in &self.candidates {
+                let (b, other_team, _) = bodies[j];
+                if j <= i || !team.hurts(other_team) {
+                    continue;
+                }
+                if let Some(time) = self.sweep_between(a, b) {
+                    contacts.push(Contact { a: a, b: b, time: time });
+                }
+            }
+        }
+
+        self.bodies = bodies;
+        contacts
+    }
+
+    /// When `a` and `b` first touched, both moving during the tick.
+    fn sweep_between(&self, a: Entity, b: Entity) -> Option<f64> {
+        let (a_transform, a_shape) = match (self.transforms.get(a), self.shape(a)) {
+            (Some(transform), Some(shape)) => (transform, shape),
+            _ => return None,
+        };
+        let (b_transform, b_shape) = match (self.transforms.get(b), self.shape(b)) {
+            (Some(transform), Some(shape)) => (transform, shape),
+            _ => return None,
+        };
+
+        // `a` moves as seen from `b`, which stays where it ends up
+        let to = a_transform.position();
+        let from = (
+            to.0 - (a_transform.rect.x - a_transform.prev.x) + (b_transform.rect.x - b_transform.prev.x),
+            to.1 - (a_transform.rect.y - a_transform.prev.y) + (b_transform.rect.y - b_transform.prev.y),
+        );
+        a_shape.sweep(from, to, b_shape, b_transform.position())
+    }
+
+    /// The rendering system: draws every visible entity, `alpha` of the way
+    /// through the tick, lowest layer first.
+    pub fn render(&self, phi: &mut Phi, alpha: f64) {
+        let mut drawn: Vec<(i32, Entity)> = self.looks.iter()
+            .filter(|&(_, look)| look.visible)
+            .map(|(entity, look)| (look.layer, entity))
+            .collect();
+        drawn.sort_by_key(|&(layer, entity)| (layer, entity.index()));
+
+        for (_, entity) in drawn {
+            let (look, rect) = match (self.looks.get(entity), self.transforms.get(entity)) {
+                (Some(look), Some(transform)) => (look, transform.at(alpha)),
+                _ => continue,
+            };
+
+            match look.visual {
+                Visual::Sprite(ref sprite) => phi.renderer.copy_sprite(sprite, rect),
+                Visual::Animation(ref animation) => phi.renderer.copy_sprite(animation, rect),
+                Visual::Fill(color) => {
+                    phi.renderer.set_draw_color(color);
+                    phi.renderer.fill_rect(rect);
+                },
+            }
+        }
+    }
+
+    /// Draws the bounds of every hitbox, to see what collides.
+    pub fn render_hitboxes(&self, phi: &mut Phi, alpha: f64) {
+        phi.renderer.set_draw_color(Color::RGBA(200, 200, 50, 120));
+        for (entity, transform) in self.transforms.iter() {
+            if let Some(shape) = self.shape(entity) {
+                let rect = transform.at(alpha);
+                phi.renderer.fill_rect(shape.bounds().moved_by(rect.x, rect.y));
+            }
+        }
+    }
+}
+
+/// The vector of length 1 pointing the same way as `(x, y)`, if any.
+fn unit(x: f64, y: f64) -> Option<(f64, f64)> {
+    let len = (x * x + y * y).sqrt();
+    if len > 0.0 {
+        Some((x / len, y / len))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::phi::data::Rectangle;
+    use ::phi::data::Shape;
+    use super::{Aim, Charge, Components, Curve, Death, Entity, Gun, Halt, Health, Hitbox, Path, Points, Team,
+                Transform, Velocity, World};
+    use ::std::rc::Rc;
+
+    fn moving(world: &mut World, rect: Rectangle, x: f64, y: f64) -> Entity {
+        let entity = world.create();
+        world.transforms.insert(entity, Transform::new(rect));
+        world.velocities.insert(entity, Velocity { x: x, y: y });
+        entity
+    }
+
+    fn area() -> Rectangle {
+        Rectangle::with_size(800.0, 600.0)
+    }
+
+    #[test]
+    fn destroyed_entities_lose_their_components() {
+        let mut world = World::new();
+        let mut names = Components::new();
+        let a = world.create();
+        let b = world.create();
+        names.insert(a, "a");
+        names.insert(b, "b");
+
+        world.destroy(a);
+        world.retain_alive(&mut names);
+        assert_eq!(names.len(), 1);
+        assert_eq!(names.get(b), Some(&"b"));
+
+        // the next entity in the slot of `a` starts without any
+        let c = world.create();
+        assert_eq!(c.index(), a.index());
+        assert_eq!(names.get(c), None);
+        assert_eq!(names.get(a), None);
+        names.insert(c, "c");
+        assert_eq!(names.get(c), Some(&"c"));
+        assert_eq!(names.get(a), None);
+    }
+
+    #[test]
+    fn paths_move_entities_up_and_down() {
+        let mut world = World::new();
+        let entity = moving(&mut world, Rectangle::with_size(10.0, 10.0).moved(400.0, 300.0), -100.0, 0.0);
+        world.paths.insert(entity, Path::new(300.0, Curve::Sine { amplitude: 20.0, angular_vel: ::std::f64::consts::PI }));
+
+        world.move_all(0.5, area());
+        world.follow_paths(0.5);
+        let transform = *world.transforms.get(entity).unwrap();
+        assert_eq!(transform.rect.x, 350.0);
+        assert!((transform.rect.y - 320.0).abs() < 1e-9);
+        assert_eq!(transform.prev.y, 300.0);
+    }
+
+    #[test]
+    fn charges_turn_towards_the_target() {
+        let mut world = World::new();
+        let entity = moving(&mut world, Rectangle::with_size(10.0, 10.0).moved(500.0, 95.0), -100.0, 0.0);
+        world.charges.insert(entity, Charge { at_x: 400.0, speed: 300.0 });
+
+        world.charge(Some((0.0, 400.0)));
+        assert_eq!(world.velocities.get(entity), Some(&Velocity { x: -100.0, y: 0.0 }));
+
+        world.transforms.insert(entity, Transform::new(Rectangle::with_size(10.0, 10.0).moved(395.0, 95.0)));
+        world.charge(Some((100.0, 500.0)));
+        let velocity = *world.velocities.get(entity).unwrap();
+        assert!((velocity.x - -300.0 * 0.6).abs() < 1e-9);
+        assert!((velocity.y - 300.0 * 0.8).abs() < 1e-9);
+        assert!(world.charges.get(entity).is_none());
+    }
+
+    #[test]
+    fn charges_keep_their_way_without_a_target() {
+        let mut world = World::new();
+        let entity = moving(&mut world, Rectangle::with_size(10.0, 10.0).moved(300.0, 100.0), -100.0, 0.0);
+        world.charges.insert(entity, Charge { at_x: 400.0, speed: 300.0 });
+
+        world.charge(None);
+        assert_eq!(world.velocities.get(entity), Some(&Velocity { x: -300.0, y: 0.0 }));
+    }
+
+    #[test]
+    fn halts_stop_where_they_halt_until_their_time_is_up() {
+        let mut world = World::new();
+        let entity = moving(&mut world, Rectangle::with_size(10.0, 10.0).moved(420.0, 100.0), -100.0, 0.0);
+        world.halts.insert(entity, Halt { at_x: 400.0, left: 1.0, speed: 100.0 });
+
+        world.move_all(0.5, area());
+        world.halt(0.5);
+        assert_eq!(world.transforms.get(entity).unwrap().rect.x, 400.0);
+        assert_eq!(world.velocities.get(entity).unwrap().x, 0.0);
+
+        world.move_all(0.5, area());
+        world.halt(0.5);
+        assert_eq!(world.transforms.get(entity).unwrap().rect.x, 400.0);
+        assert_eq!(world.velocities.get(entity).unwrap().x, -100.0);
+    }
+
+    #[test]
+    fn guns_fire_in_bursts_once_in_the_area() {
+        let mut world = World::new();
+        let entity = moving(&mut world, Rectangle::with_size(10.0, 10.0).moved(810.0, 100.0), 0.0, 0.0);
+        world.guns.insert(entity, Gun::new(Aim::Target(-1.0, 0.0), (5.0, 5.0), 2.0, 0.5).bursts(2, 0.25));
+
+        // out of the area, the gun keeps time without firing
+        assert_eq!(world.fire(0.5, area(), None), vec![]);
+
+        world.transforms.insert(entity, Transform::new(Rectangle::with_size(10.0, 10.0).moved(400.0, 100.0)));
+        assert_eq!(world.fire(0.25, area(), Some((405.0, 205.0))), vec![((405.0, 105.0), (0.0, 1.0))]);
+        assert_eq!(world.fire(1.0, area(), None), vec![]);
+        assert_eq!(world.fire(1.0, area(), None), vec![((405.0, 105.0), (-1.0, 0.0))]);
+        assert_eq!(world.fire(0.25, area(), None), vec![((405.0, 105.0), (-1.0, 0.0))]);
+    }
+
+    #[test]
+    fn rings_go_all_around() {
+        let mut world = World::new();
+        let entity = moving(&mut world, Rectangle::with_size(10.0, 10.0).moved(400.0, 100.0), 0.0, 0.0);
+        world.guns.insert(entity, Gun::new(Aim::Ring { bullets: 4, spin: 0.0 }, (0.0, 0.0), 1.0, 0.0));
+
+        let dirs: Vec<(i32, i32)> = world.fire(0.1, area(), None).iter()
+            .map(|&(_, (x, y))| (x.round() as i32, y.round() as i32))
+            .collect();
+        assert_eq!(dirs, vec![(1, 0), (0, 1), (-1, 0), (0, -1)]);
+    }
+
+    #[test]
+    fn hurting_keeps_the_deaths() {
+        let mut world = World::new();
+        let bullet = world.create();
+        let entity = moving(&mut world, Rectangle::with_size(10.0, 10.0).moved(100.0, 100.0), 0.0, 0.0);
+        world.health.insert(entity, Health(2));
+        world.points.insert(entity, Points(150));
+
+        assert!(world.hurt(entity, 1, bullet));
+        assert_eq!(world.deaths, vec![]);
+        assert!(world.hurt(entity, 1, bullet));
+        assert_eq!(world.deaths, vec![Death { entity: entity, by: bullet, center: (105.0, 105.0), points: 150 }]);
+        assert!(!world.entities.is_alive(entity));
+
+        // nothing is left to hurt
+        assert!(!world.hurt(entity, 1, bullet));
+        assert!(!world.hurt(bullet, 1, bullet));
+    }
+
+    #[test]
+    fn entities_without_shapes_collide_with_nothing() {
+        let mut world = World::new();
+        let rect = Rectangle::with_size(10.0, 10.0).moved(100.0, 100.0);
+        let ship = moving(&mut world, rect, 0.0, 0.0);
+        world.hitboxes.insert(ship, Hitbox::new(Shape::Rect(Rectangle::with_size(10.0, 10.0))));
+        world.teams.insert(ship, Team::Player);
+        let ghost = moving(&mut world, rect, 0.0, 0.0);
+        world.hitboxes.insert(ghost, Hitbox::frames(Rc::new(vec![])));
+        world.teams.insert(ghost, Team::Enemy);
+
+        assert!(world.shape(ghost).is_none());
+        assert_eq!(world.collisions(area()), vec![]);
+    }
+}
@@ -0,0 +1,39 @@
+use ::std::fmt;
+
+/// Something which went wrong in phi, described well enough to be shown to
+/// the player.
+#[derive(Clone, Debug)]
+pub enum Error {
+    /// SDL, or one of its subsystems, could not be initialised.
+    Sdl(String),
+    /// A file which the game needs, such as an image or a replay, could not
+    /// be loaded.
+    Asset(String),
+    /// Some text could not be rendered.
+    Font(String),
+    /// A shape could not be used where it was needed, for example because
+    /// it does not fit in the window.
+    Geometry(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Sdl(ref message) => write!(f, "SDL error: {}", message),
+            Error::Asset(ref message) => write!(f, "Asset error: {}", message),
+            Error::Font(ref message) => write!(f, "Font error: {}", message),
+            Error::Geometry(ref message) => write!(f, "Geometry error: {}", message),
+        }
+    }
+}
+
+impl ::std::error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Sdl(ref message) |
+            Error::Asset(ref message) |
+            Error::Font(ref message) |
+            Error::Geometry(ref message) => message,
+        }
+    }
+}
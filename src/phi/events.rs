@@ -6,10 +6,42 @@ macro_rules! struct_events {
 )
 => {
 
-        use ::sdl2::EventPump;
+        use ::sdl2::{EventPump, GameControllerSubsystem};
+        use ::sdl2::controller::{Axis, Button, GameController};
+        use ::sdl2::keyboard::Keycode;
+        use ::sdl2::mouse::Mouse;
+        use ::std::collections::HashSet;
+        use ::phi::input::{Action, Bindings, InputEvent};
+        use ::phi::replay::Replay;
+
+        /// How far a stick must be pushed to count as a held direction,
+        /// for example when navigating menus.
+        const STICK_PRESS: f64 = 0.5;
+
+        /// The actions which the left stick holds when pushed.
+        const STICK_ACTIONS: [Action; 4] = [Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight];
+
+        /// The `which` of mouse events which SDL synthesizes from touches.
+        /// We handle touches ourselves, so those are ignored.
+        const TOUCH_MOUSE_ID: u32 = 0xFFFF_FFFF;
 
         pub struct ImmediateEvents {
             resize: Option<(u32, u32)>,
+            /// Every key which went down during this tick.
+            pub pressed: Vec<Keycode>,
+            /// Every controller button which went down during this tick.
+            pub pressed_buttons: Vec<Button>,
+            /// The `Move` actions which the left stick started holding
+            /// during this tick.
+            pub stick_pressed: Vec<Action>,
+            /// Whether the mouse moved, or a finger was dragged, during this tick.
+            pub mouse_moved: bool,
+            /// Mouse buttons which went down during this tick. Touching the
+            /// screen presses `Mouse::Left`.
+            pub mouse_pressed: Vec<Mouse>,
+            pub mouse_released: Vec<Mouse>,
+            /// How far the wheel was scrolled horizontally and vertically.
+            pub wheel: (i32, i32),
             // For every keyboard event, we will have an Option<bool>
             $( pub $k_alias: Option<bool> , )*
             $( pub $e_alias: bool ),*
@@ -19,6 +51,13 @@ macro_rules! struct_events {
             pub fn new() -> ImmediateEvents {
                 ImmediateEvents {
                     resize: None,
+                    pressed: vec![],
+                    pressed_buttons: vec![],
+                    stick_pressed: vec![],
+                    mouse_moved: false,
+                    mouse_pressed: vec![],
+                    mouse_released: vec![],
+                    wheel: (0, 0),
                     $( $k_alias: None , )*
                     $( $e_alias: false ),*
                 }
@@ -28,6 +67,20 @@ macro_rules! struct_events {
         pub struct Events {
             pump: EventPump,
             pub now: ImmediateEvents,
+            pub bindings: Bindings,
+            held: HashSet<Keycode>,
+            held_buttons: HashSet<Button>,
+            /// Position of every controller axis, between -1 and 1.
+            axes: ::std::collections::HashMap<Axis, f64>,
+            controller_subsystem: Option<GameControllerSubsystem>,
+            controllers: Vec<GameController>,
+            /// Last known position of the mouse, or of the finger touching
+            /// the screen, in pixels.
+            pub mouse: (f64, f64),
+            /// Mouse buttons currently held down.
+            pub mouse_buttons: HashSet<Mouse>,
+            recording: Option<Replay>,
+            playback: Option<Replay>,
             $(pub $k_alias: bool ),*
         }
 
@@ -37,29 +90,276 @@ macro_rules! struct_events {
                 Events {
                     pump: pump,
                     now: ImmediateEvents::new(),
+                    bindings: Bindings::default(),
+                    held: HashSet::new(),
+                    held_buttons: HashSet::new(),
+                    axes: ::std::collections::HashMap::new(),
+                    controller_subsystem: None,
+                    controllers: vec![],
+                    mouse: (0.0, 0.0),
+                    mouse_buttons: HashSet::new(),
+                    recording: None,
+                    playback: None,
                     $( $k_alias: false ),*
                 }
             }
 
-            pub fn is_key_diagonal(&self) -> bool {
-                (self.key_up ^ self.key_down) && (self.key_left ^ self.key_right)
+            /// Starts listening to game controllers. They are opened as they
+            /// get connected; SDL reports those which are already plugged in
+            /// as connected on the first pump.
+            pub fn enable_controllers(&mut self, subsystem: GameControllerSubsystem) {
+                self.controller_subsystem = Some(subsystem);
+            }
+
+            fn open_controller(&mut self, id: u32) {
+                if let Some(ref subsystem) = self.controller_subsystem {
+                    if subsystem.is_game_controller(id) {
+                        match subsystem.open(id) {
+                            Ok(controller) => {
+                                println!("Controller connected: {}", controller.name());
+                                self.controllers.push(controller);
+                            },
+                            Err(e) => println!("Could not open controller {}: {}", id, e),
+                        }
+                    }
+                }
+            }
+
+            /// Whether any key or button bound to `action` is being held down.
+            /// Moving the left stick far enough also holds the `Move` actions.
+            pub fn is_down(&self, action: Action) -> bool {
+                self.stick_holds(action) ||
+                self.bindings.keys(action).iter().any(|key| self.held.contains(key)) ||
+                self.bindings.buttons(action).iter().any(|button| self.held_buttons.contains(button))
+            }
+
+            /// Whether a key or button bound to `action` was pressed during this tick.
+            /// Pushing the left stick far enough presses the `Move` actions, so
+            /// that it can navigate menus.
+            pub fn pressed(&self, action: Action) -> bool {
+                self.now.stick_pressed.contains(&action) ||
+                self.now.pressed.iter().any(|key| self.bindings.is_bound(action, *key)) ||
+                self.now.pressed_buttons.iter().any(|button| self.bindings.is_button_bound(action, *button))
+            }
+
+            /// Whether `button` went down during this tick.
+            pub fn mouse_pressed(&self, button: Mouse) -> bool {
+                self.now.mouse_pressed.contains(&button)
+            }
+
+            fn press_mouse(&mut self, button: Mouse, pos: (f64, f64)) {
+                self.mouse = pos;
+                if self.mouse_buttons.insert(button) {
+                    self.now.mouse_pressed.push(button);
+                }
+            }
+
+            fn release_mouse(&mut self, button: Mouse, pos: (f64, f64)) {
+                self.mouse = pos;
+                if self.mouse_buttons.remove(&button) {
+                    self.now.mouse_released.push(button);
+                }
+            }
+
+            /// Whether the left stick is pushed far enough to hold `action`.
+            fn stick_holds(&self, action: Action) -> bool {
+                let (x, y) = self.stick();
+                match action {
+                    Action::MoveUp => y <= -STICK_PRESS,
+                    Action::MoveDown => y >= STICK_PRESS,
+                    Action::MoveLeft => x <= -STICK_PRESS,
+                    Action::MoveRight => x >= STICK_PRESS,
+                    _ => false,
+                }
+            }
+
+            /// Position of the left stick, each coordinate being between -1 and 1.
+            /// Within the deadzone it is `(0, 0)`, and the rest of the range is
+            /// rescaled so that movement starts smoothly at its edge.
+            pub fn stick(&self) -> (f64, f64) {
+                let x = *self.axes.get(&Axis::LeftX).unwrap_or(&0.0);
+                let y = *self.axes.get(&Axis::LeftY).unwrap_or(&0.0);
+                let magnitude = (x * x + y * y).sqrt();
+                let deadzone = self.bindings.deadzone;
+
+                if magnitude <= deadzone {
+                    return (0.0, 0.0);
+                }
+
+                let scale = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0) / magnitude;
+                (x * scale, y * scale)
+            }
+
+            /// The direction in which the player wants to move, with a length
+            /// of at most 1. The stick takes precedence over the digital
+            /// inputs, which always give a full-length vector.
+            pub fn movement(&self) -> (f64, f64) {
+                let stick = self.stick();
+                if stick != (0.0, 0.0) {
+                    return stick;
+                }
+
+                let axis = |neg, pos| match (self.is_down(neg), self.is_down(pos)) {
+                    (true, false) => -1.0,
+                    (false, true) => 1.0,
+                    _ => 0.0,
+                };
+
+                let x = axis(Action::MoveLeft, Action::MoveRight);
+                let y = axis(Action::MoveUp, Action::MoveDown);
+
+                if x != 0.0 && y != 0.0 {
+                    (x / 2.0f64.sqrt(), y / 2.0f64.sqrt())
+                } else {
+                    (x, y)
+                }
             }
 
             /// Update the events.
-            pub fn pump(&mut self, renderer: &mut ::sdl2::render::Renderer) {
+            ///
+            /// While a replay is being played back, the input devices are
+            /// ignored (except for quitting) in favour of the recorded inputs.
+            pub fn pump(&mut self, renderer: &::phi::backend::Backend) {
                 self.now = ImmediateEvents::new();
 
-                for event in self.pump.poll_iter() {
-                    use ::sdl2::event::Event::*;
-                    use ::sdl2::event::WindowEventId::Resized;
-                    use ::sdl2::keyboard::Keycode::*;
-                    match event {
-                        Window { win_event_id: Resized, .. } => {
-                            self.now.resize = Some(renderer.output_size().unwrap());
-                        },
-                        KeyDown { keycode, .. } => match keycode {
+                let mut inputs = vec![];
+                let events: Vec<_> = self.pump.poll_iter().collect();
+                for event in events {
+                    self.translate(event, renderer, &mut inputs);
+                }
+
+                let finished = match self.playback {
+                    Some(ref mut playback) => {
+                        let quit = inputs.into_iter()
+                            .filter(|input| *input == InputEvent::Other("quit".to_string()));
+                        inputs = playback.next_tick();
+                        inputs.extend(quit);
+                        playback.is_finished()
+                    },
+                    None => false,
+                };
+
+                if finished {
+                    println!("Replay finished, switching to live input");
+                    self.playback = None;
+                }
+
+                if let Some(ref mut recording) = self.recording {
+                    recording.push_tick(inputs.clone());
+                }
+
+                for input in inputs {
+                    self.apply(input);
+                }
+            }
+
+            /// Starts recording the inputs of every tick into `replay`.
+            pub fn record(&mut self, replay: Replay) {
+                self.recording = Some(replay);
+            }
+
+            /// Stops recording, returning what was recorded.
+            pub fn take_recording(&mut self) -> Option<Replay> {
+                self.recording.take()
+            }
+
+            /// Feeds the inputs of `replay` to the views instead of those of
+            /// the player, one tick at a time.
+            pub fn play(&mut self, replay: Replay) {
+                self.playback = Some(replay);
+            }
+
+            /// Turns an SDL event into the inputs it stands for.
+            fn translate(&mut self, event: ::sdl2::event::Event,
+                         renderer: &::phi::backend::Backend, inputs: &mut Vec<InputEvent>) {
+                use ::sdl2::event::Event::*;
+                use ::sdl2::event::WindowEventId::Resized;
+
+                match event {
+                    Window { win_event_id: Resized, .. } => {
+                        let (w, h) = renderer.output_size();
+                        inputs.push(InputEvent::Resize(w as u32, h as u32));
+                    },
+                    KeyDown { keycode: Some(code), repeat: false, .. } => {
+                        inputs.push(InputEvent::KeyDown(code));
+                    },
+                    KeyUp { keycode: Some(code), .. } => {
+                        inputs.push(InputEvent::KeyUp(code));
+                    },
+                    MouseMotion { which, x, y, .. } => {
+                        if which != TOUCH_MOUSE_ID {
+                            inputs.push(InputEvent::MouseMove(x as f64, y as f64));
+                        }
+                    },
+                    MouseButtonDown { which, mouse_btn, x, y, .. } => {
+                        if which != TOUCH_MOUSE_ID {
+                            inputs.push(InputEvent::MouseDown(mouse_btn, x as f64, y as f64));
+                        }
+                    },
+                    MouseButtonUp { which, mouse_btn, x, y, .. } => {
+                        if which != TOUCH_MOUSE_ID {
+                            inputs.push(InputEvent::MouseUp(mouse_btn, x as f64, y as f64));
+                        }
+                    },
+                    MouseWheel { x, y, .. } => {
+                        inputs.push(InputEvent::Wheel(x, y));
+                    },
+                    // touch positions are normalized between 0 and 1
+                    FingerDown { x, y, .. } => {
+                        let (w, h) = renderer.output_size();
+                        inputs.push(InputEvent::MouseDown(Mouse::Left, x as f64 * w, y as f64 * h));
+                    },
+                    FingerMotion { x, y, .. } => {
+                        let (w, h) = renderer.output_size();
+                        inputs.push(InputEvent::MouseMove(x as f64 * w, y as f64 * h));
+                    },
+                    FingerUp { x, y, .. } => {
+                        let (w, h) = renderer.output_size();
+                        inputs.push(InputEvent::MouseUp(Mouse::Left, x as f64 * w, y as f64 * h));
+                    },
+                    ControllerDeviceAdded { which, .. } => {
+                        self.open_controller(which as u32);
+                    },
+                    ControllerDeviceRemoved { .. } => {
+                        self.controllers.retain(|controller| controller.attached());
+                        if self.controllers.is_empty() {
+                            inputs.push(InputEvent::ControllersGone);
+                        }
+                    },
+                    ControllerButtonDown { button, .. } => {
+                        inputs.push(InputEvent::ButtonDown(button));
+                    },
+                    ControllerButtonUp { button, .. } => {
+                        inputs.push(InputEvent::ButtonUp(button));
+                    },
+                    ControllerAxisMotion { axis, value, .. } => {
+                        inputs.push(InputEvent::Axis(axis, value as f64 / 32_767.0));
+                    },
+                    $(
+                        $e_sdl => {
+                            inputs.push(InputEvent::Other(stringify!($e_alias).to_string()));
+                        }
+                    )*,
+                    _ => {}
+                }
+            }
+
+            /// Updates the state of the events with a single input.
+            fn apply(&mut self, input: InputEvent) {
+                use ::sdl2::keyboard::Keycode::*;
+
+                match input {
+                    InputEvent::Resize(w, h) => {
+                        self.now.resize = Some((w, h));
+                    },
+                    InputEvent::KeyDown(code) => {
+                        if self.held.insert(code) {
+                            self.now.pressed.push(code);
+                        }
+
+                        match code {
                             $(
-                                Some($k_sdl) => {
+                                $k_sdl => {
                                     if !self.$k_alias {
                                         self.now.$k_alias = Some(true);
                                     }
@@ -67,23 +367,59 @@ macro_rules! struct_events {
                                 }
                             ),*
                             _ => {}
-                        },
-                        KeyUp { keycode, .. } => match keycode {
+                        }
+                    },
+                    InputEvent::KeyUp(code) => {
+                        self.held.remove(&code);
+
+                        match code {
                             $(
-                                Some($k_sdl) => {
+                                $k_sdl => {
                                     self.now.$k_alias = Some(false);
                                     self.$k_alias = false;
                                 }
                             ),*
                             _ => {}
-                        },
+                        }
+                    },
+                    InputEvent::MouseMove(x, y) => {
+                        self.mouse = (x, y);
+                        self.now.mouse_moved = true;
+                    },
+                    InputEvent::MouseDown(button, x, y) => self.press_mouse(button, (x, y)),
+                    InputEvent::MouseUp(button, x, y) => self.release_mouse(button, (x, y)),
+                    InputEvent::Wheel(x, y) => {
+                        self.now.wheel.0 += x;
+                        self.now.wheel.1 += y;
+                    },
+                    InputEvent::ControllersGone => {
+                        self.held_buttons.clear();
+                        self.axes.clear();
+                    },
+                    InputEvent::ButtonDown(button) => {
+                        if self.held_buttons.insert(button) {
+                            self.now.pressed_buttons.push(button);
+                        }
+                    },
+                    InputEvent::ButtonUp(button) => {
+                        self.held_buttons.remove(&button);
+                    },
+                    InputEvent::Axis(axis, value) => {
+                        let held: Vec<bool> = STICK_ACTIONS.iter().map(|&action| self.stick_holds(action)).collect();
+                        self.axes.insert(axis, value);
+                        for (&action, &was_held) in STICK_ACTIONS.iter().zip(held.iter()) {
+                            if !was_held && self.stick_holds(action) {
+                                self.now.stick_pressed.push(action);
+                            }
+                        }
+                    },
+                    InputEvent::Other(name) => {
                         $(
-                            $e_sdl => {
+                            if name == stringify!($e_alias) {
                                 self.now.$e_alias = true;
                             }
-                        )*,
-                        _ => {}
-                    }
+                        )*
+                    },
                 }
             }
         }
@@ -1,15 +1,14 @@
-use ::phi::Phi;
+use ::phi::{Error, Phi};
+use ::phi::backend::Backend;
 use ::phi::data::Rectangle;
 use ::std::cell::RefCell;
-use ::std::path::Path;
 use ::std::rc::Rc;
-use ::sdl2::render::{Renderer, Texture};
-use ::sdl2_image::LoadTexture;
+use ::sdl2::render::Texture;
 
 /// Common inerface for rendering a graphical component
 /// to some given region of the window.
 pub trait Renderable {
-    fn render(&self, render: &mut Renderer, dest: Rectangle);
+    fn render(&self, renderer: &mut Backend, dest: Rectangle);
 }
 
 
@@ -22,9 +21,15 @@ pub struct Sprite {
 impl Sprite {
     /// Creates a new sprite by wrapping a `Texture`
     pub fn new(texture: Texture) -> Sprite {
-        let q = texture.query();
+        Sprite::shared(Rc::new(RefCell::new(texture)))
+    }
+
+    /// Creates a new sprite covering the whole of a texture which may
+    /// already be used by other sprites.
+    pub fn shared(texture: Rc<RefCell<Texture>>) -> Sprite {
+        let q = texture.borrow().query();
         Sprite {
-            tex: Rc::new(RefCell::new(texture)),
+            tex: texture,
             src: Rectangle {
                 w: q.width as f64,
                 h: q.height as f64,
@@ -34,11 +39,6 @@ impl Sprite {
         }
     }
 
-    /// Creates a new sprite from an image file.
-    pub fn load(renderer: &Renderer, path: &str) -> Option<Sprite> {
-        renderer.load_texture(Path::new(path)).ok().map(Sprite::new)
-    }
-
     /// Returns a new `Sprite` representing a subregion of the current one
     /// The provided `rect` is relative to the currently held region.
     /// Returns `Some` if the rect is valid.
@@ -60,8 +60,8 @@ impl Sprite {
 }
 
 impl Renderable for Sprite {
-    fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
-        renderer.copy(&mut self.tex.borrow_mut(), self.src.to_sdl(), dest.to_sdl());
+    fn render(&self, renderer: &mut Backend, dest: Rectangle) {
+        renderer.copy(&self.tex.borrow(), self.src, dest);
     }
 }
 
@@ -71,6 +71,9 @@ impl Renderable for Sprite {
 #[derive(Clone)]
 pub struct AnimatedSprite {
     sprites: Rc<Vec<Sprite>>,
+    /// How long each frame is shown, if they are not all shown for
+    /// `frame_delay`.
+    durations: Option<Rc<Vec<f64>>>,
     frame_delay: f64,
     current_time: f64,
 }
@@ -88,13 +91,28 @@ impl AnimatedSprite {
     pub fn new(sprites: Vec<Sprite>, frame_delay: f64) -> AnimatedSprite {
         AnimatedSprite {
             sprites: Rc::new(sprites),
+            durations: None,
             frame_delay: frame_delay,
             current_time: 0.0,
         }
     }
 
-    pub fn load_frames(phi: &mut Phi, descr: AnimatedSpriteDescr) -> Vec<Sprite> {
-        let spritesheet = Sprite::load(&mut phi.renderer, descr.image_path).unwrap();
+    /// Creates an animation in which every frame is shown for its own
+    /// duration, in seconds.
+    pub fn with_durations(sprites: Vec<Sprite>, durations: Vec<f64>) -> AnimatedSprite {
+        if durations.len() != sprites.len() || durations.iter().any(|&d| d <= 0.0) {
+            panic!("Passed invalid durations to AnimatedSprite::with_durations");
+        }
+        AnimatedSprite {
+            sprites: Rc::new(sprites),
+            durations: Some(Rc::new(durations)),
+            frame_delay: 0.0,
+            current_time: 0.0,
+        }
+    }
+
+    pub fn load_frames(phi: &mut Phi, descr: AnimatedSpriteDescr) -> Result<Vec<Sprite>, Error> {
+        let spritesheet = try!(phi.sprite(descr.image_path));
 
         let mut frames = Vec::with_capacity(descr.total_frames);
 
@@ -104,20 +122,17 @@ impl AnimatedSprite {
                     break;
                 }
 
-                frames.push(spritesheet.region(Rectangle {
+                frames.push(try!(spritesheet.region(Rectangle {
                     w: descr.frame_w,
                     h: descr.frame_h,
                     x: descr.frame_w * xth as f64,
                     y: descr.frame_h * yth as f64,
-                }).unwrap());
+                }).ok_or(Error::Geometry(
+                    format!("{} is too small for frame {}", descr.image_path, frames.len())))));
             }
         }
 
-        frames
-    }
-
-    pub fn load_frames_with_fps(phi: &mut Phi, fps: f64, descr: AnimatedSpriteDescr) -> AnimatedSprite {
-        Self::with_fps(Self::load_frames(phi, descr), fps)
+        Ok(frames)
     }
 
     pub fn with_fps(sprites: Vec<Sprite>, fps: f64) -> AnimatedSprite {
@@ -131,7 +146,23 @@ impl AnimatedSprite {
         self.sprites.len()
     }
 
+    /// The size of a single frame.
+    pub fn size(&self) -> (f64, f64) {
+        self.sprites[0].size()
+    }
+
+    /// How long it takes to show every frame once, in seconds.
+    pub fn duration(&self) -> f64 {
+        match self.durations {
+            Some(ref durations) => durations.iter().fold(0.0, |total, d| total + d),
+            None => self.frames() as f64 * self.frame_delay,
+        }
+    }
+
+    /// Shows every frame for `frame_delay` seconds, replacing the durations
+    /// of the individual frames if there were any.
     pub fn set_frame_delay(&mut self, frame_delay: f64) {
+        self.durations = None;
         self.frame_delay = frame_delay;
     }
 
@@ -145,18 +176,35 @@ impl AnimatedSprite {
     pub fn add_time(&mut self, dt: f64) {
         self.current_time += dt;
         if self.current_time < 0.0 {
-            self.current_time = (self.frames() - 1) as f64 * self.frame_delay;
+            let last = match self.durations {
+                Some(ref durations) => durations[durations.len() - 1],
+                None => self.frame_delay,
+            };
+            self.current_time = self.duration() - last;
         }
     }
 
-    fn current_frame(&self) -> usize {
-        (self.current_time / self.frame_delay) as usize % self.frames()
+    /// The index of the frame which is shown at the moment.
+    pub fn current_frame(&self) -> usize {
+        match self.durations {
+            Some(ref durations) => {
+                let mut time = self.current_time % self.duration();
+                for (i, duration) in durations.iter().enumerate() {
+                    if time < *duration {
+                        return i;
+                    }
+                    time -= *duration;
+                }
+                durations.len() - 1
+            },
+            None => (self.current_time / self.frame_delay) as usize % self.frames(),
+        }
     }
 
 }
 
 impl Renderable for AnimatedSprite {
-    fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
+    fn render(&self, renderer: &mut Backend, dest: Rectangle) {
         let sprite = &self.sprites[self.current_frame()];
         sprite.render(renderer, dest);
     }
@@ -166,7 +214,7 @@ pub trait CopySprite<T> {
     fn copy_sprite(&mut self, sprite: &T, dest: Rectangle);
 }
 
-impl <'window, T: Renderable> CopySprite<T> for Renderer<'window> {
+impl <'window, T: Renderable> CopySprite<T> for Backend + 'window {
     fn copy_sprite(&mut self, sprite: &T, dest: Rectangle) {
         sprite.render(self, dest);
     }
@@ -0,0 +1,362 @@
+use ::sdl2::controller::{Axis, Button};
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::mouse::Mouse;
+use ::std::collections::HashMap;
+use ::std::fs::File;
+use ::std::io::{Read, Write};
+
+/// Where the player's key bindings are stored.
+pub const CONTROLS_PATH: &'static str = "controls.cfg";
+
+/// A single change in the state of the input devices, as applied to
+/// `Events`. Every SDL event we care about is turned into one of these
+/// first, so that they can be recorded and replayed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    KeyDown(Keycode),
+    KeyUp(Keycode),
+    ButtonDown(Button),
+    ButtonUp(Button),
+    /// The position of a controller axis, between -1 and 1.
+    Axis(Axis, f64),
+    /// Every controller was unplugged.
+    ControllersGone,
+    MouseMove(f64, f64),
+    MouseDown(Mouse, f64, f64),
+    MouseUp(Mouse, f64, f64),
+    Wheel(i32, i32),
+    Resize(u32, u32),
+    /// One of the other events listed in `struct_events!`, by name.
+    Other(String),
+}
+
+
+/// What the player wants to do, independently of the keys they press for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    SelectWeapon(u8),
+    Pause,
+    Confirm,
+}
+
+/// Number of weapons that can be selected with `Action::SelectWeapon`,
+/// starting at 1.
+pub const WEAPONS: u8 = 3;
+
+/// Prefix of controller buttons in the controls file, e.g. `pad:a`.
+const BUTTON_PREFIX: &'static str = "pad:";
+/// What the comma key is called in the controls file, where its own name
+/// would split the list of keys.
+const COMMA_NAME: &'static str = "Comma";
+
+/// How far, between 0 and 1, a stick must be pushed before it counts.
+pub const DEFAULT_DEADZONE: f64 = 0.25;
+
+impl Action {
+    /// Every action which can be bound, in the order they are listed in
+    /// the controls menu.
+    pub fn all() -> Vec<Action> {
+        let mut actions = vec![
+            Action::MoveUp,
+            Action::MoveDown,
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::Fire,
+        ];
+        for n in 1..WEAPONS + 1 {
+            actions.push(Action::SelectWeapon(n));
+        }
+        actions.push(Action::Pause);
+        actions.push(Action::Confirm);
+        actions
+    }
+
+    /// The name of the action in the controls file.
+    pub fn name(&self) -> String {
+        match *self {
+            Action::MoveUp => "move_up".to_string(),
+            Action::MoveDown => "move_down".to_string(),
+            Action::MoveLeft => "move_left".to_string(),
+            Action::MoveRight => "move_right".to_string(),
+            Action::Fire => "fire".to_string(),
+            Action::SelectWeapon(n) => format!("select_weapon_{}", n),
+            Action::Pause => "pause".to_string(),
+            Action::Confirm => "confirm".to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::all().into_iter().find(|action| action.name() == name)
+    }
+
+    /// A human readable name, as shown in the controls menu.
+    pub fn label(&self) -> String {
+        match *self {
+            Action::MoveUp => "Move up".to_string(),
+            Action::MoveDown => "Move down".to_string(),
+            Action::MoveLeft => "Move left".to_string(),
+            Action::MoveRight => "Move right".to_string(),
+            Action::Fire => "Fire".to_string(),
+            Action::SelectWeapon(n) => format!("Weapon {}", n),
+            Action::Pause => "Pause".to_string(),
+            Action::Confirm => "Confirm".to_string(),
+        }
+    }
+}
+
+
+/// Maps every `Action` to the keys and controller buttons which trigger it.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: HashMap<Action, Vec<Keycode>>,
+    buttons: HashMap<Action, Vec<Button>>,
+    pub deadzone: f64,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        let mut keys = HashMap::new();
+        keys.insert(Action::MoveUp, vec![Keycode::Up]);
+        keys.insert(Action::MoveDown, vec![Keycode::Down]);
+        keys.insert(Action::MoveLeft, vec![Keycode::Left]);
+        keys.insert(Action::MoveRight, vec![Keycode::Right]);
+        keys.insert(Action::Fire, vec![Keycode::Space]);
+        keys.insert(Action::SelectWeapon(1), vec![Keycode::Num1]);
+        keys.insert(Action::SelectWeapon(2), vec![Keycode::Num2]);
+        keys.insert(Action::SelectWeapon(3), vec![Keycode::Num3]);
+        keys.insert(Action::Pause, vec![Keycode::Escape]);
+        keys.insert(Action::Confirm, vec![Keycode::Return, Keycode::Space]);
+
+        let mut buttons = HashMap::new();
+        buttons.insert(Action::MoveUp, vec![Button::DPadUp]);
+        buttons.insert(Action::MoveDown, vec![Button::DPadDown]);
+        buttons.insert(Action::MoveLeft, vec![Button::DPadLeft]);
+        buttons.insert(Action::MoveRight, vec![Button::DPadRight]);
+        buttons.insert(Action::Fire, vec![Button::A, Button::RightShoulder]);
+        buttons.insert(Action::SelectWeapon(1), vec![Button::X]);
+        buttons.insert(Action::SelectWeapon(2), vec![Button::Y]);
+        buttons.insert(Action::SelectWeapon(3), vec![Button::B]);
+        buttons.insert(Action::Pause, vec![Button::Start]);
+        buttons.insert(Action::Confirm, vec![Button::A]);
+
+        Bindings {
+            keys: keys,
+            buttons: buttons,
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+impl Bindings {
+    /// Loads the bindings from a file made of lines such as
+    /// `fire = Space, Return, pad:a`, where controller buttons are prefixed
+    /// with `pad:` and the comma key is written `Comma`. The stick deadzone is set with `deadzone = 0.25`, from 0
+    /// up to, but not including, 1.
+    /// Actions which are not listed keep their default bindings, as do the
+    /// buttons of actions listed without any. Lines starting with `#` are
+    /// ignored.
+    pub fn load(path: &str) -> Result<Bindings, String> {
+        let mut contents = String::new();
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|e| format!("Could not read {}: {}", path, e)));
+
+        Bindings::parse(&contents, path)
+    }
+
+    /// Parses bindings in the format described in `load`. `origin` is only
+    /// used to make error messages point to the right place.
+    pub fn parse(contents: &str, origin: &str) -> Result<Bindings, String> {
+        let mut bindings = Bindings::default();
+
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap().trim();
+            let keys = try!(parts.next()
+                .ok_or(format!("{}:{}: expected `action = keys`", origin, i + 1)));
+
+            if name == "deadzone" {
+                // NaN fails the comparisons too
+                bindings.deadzone = match keys.trim().parse::<f64>() {
+                    Ok(deadzone) if deadzone >= 0.0 && deadzone < 1.0 => deadzone,
+                    _ => return Err(format!("{}:{}: the deadzone must be between 0 and 1", origin, i + 1)),
+                };
+                continue;
+            }
+
+            let action = try!(Action::from_name(name)
+                .ok_or(format!("{}:{}: unknown action `{}`", origin, i + 1, name)));
+
+            let mut codes = vec![];
+            let mut buttons = vec![];
+            for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+                if key.starts_with(BUTTON_PREFIX) {
+                    buttons.push(try!(Button::from_string(&key[BUTTON_PREFIX.len()..])
+                        .ok_or(format!("{}:{}: unknown button `{}`", origin, i + 1, key))));
+                } else {
+                    codes.push(try!(key_from_name(key)
+                        .ok_or(format!("{}:{}: unknown key `{}`", origin, i + 1, key))));
+                }
+            }
+
+            bindings.set(action, codes);
+            if !buttons.is_empty() {
+                bindings.set_buttons(action, buttons);
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    /// Writes the bindings in the format read by `load`.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        File::create(path)
+            .and_then(|mut file| file.write_all(self.to_config().as_bytes()))
+            .map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    /// The bindings in the format read by `parse`, one action per line.
+    pub fn to_config(&self) -> String {
+        let mut contents = format!("deadzone = {}\n", self.deadzone);
+        for action in Action::all() {
+            let mut names: Vec<String> = self.keys(action).iter()
+                .map(|&key| key_name(key))
+                .collect();
+            names.extend(self.buttons(action).iter()
+                .map(|button| format!("{}{}", BUTTON_PREFIX, button.string())));
+
+            contents.push_str(&format!("{} = {}\n", action.name(), names.join(", ")));
+        }
+        contents
+    }
+
+    pub fn keys(&self, action: Action) -> &[Keycode] {
+        self.keys.get(&action).map(|keys| &keys[..]).unwrap_or(&[])
+    }
+
+    /// The names of the keys bound to `action`, separated by commas.
+    pub fn keys_label(&self, action: Action) -> String {
+        self.keys(action).iter()
+            .map(|key| key.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Replaces the keys bound to `action`.
+    pub fn set(&mut self, action: Action, keys: Vec<Keycode>) {
+        self.keys.insert(action, keys);
+    }
+
+    pub fn is_bound(&self, action: Action, key: Keycode) -> bool {
+        self.keys(action).contains(&key)
+    }
+
+    pub fn buttons(&self, action: Action) -> &[Button] {
+        self.buttons.get(&action).map(|buttons| &buttons[..]).unwrap_or(&[])
+    }
+
+    /// Replaces the controller buttons bound to `action`.
+    pub fn set_buttons(&mut self, action: Action, buttons: Vec<Button>) {
+        self.buttons.insert(action, buttons);
+    }
+
+    pub fn is_button_bound(&self, action: Action, button: Button) -> bool {
+        self.buttons(action).contains(&button)
+    }
+}
+
+
+/// The name of `key` in the controls file.
+fn key_name(key: Keycode) -> String {
+    if key == Keycode::Comma {
+        COMMA_NAME.to_string()
+    } else {
+        key.name()
+    }
+}
+
+fn key_from_name(name: &str) -> Option<Keycode> {
+    if name == COMMA_NAME {
+        Some(Keycode::Comma)
+    } else {
+        Keycode::from_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::sdl2::controller::Button;
+    use ::sdl2::keyboard::Keycode;
+    use super::{Action, Bindings, DEFAULT_DEADZONE};
+
+    #[test]
+    fn parses_keys_buttons_and_deadzone() {
+        let bindings = Bindings::parse("# comment\n\
+                                        deadzone = 0.4\n\
+                                        fire = X, Return, pad:b\n\
+                                        pause = P\n", "test.cfg").unwrap();
+
+        assert_eq!(bindings.deadzone, 0.4);
+        assert_eq!(bindings.keys(Action::Fire), &[Keycode::X, Keycode::Return]);
+        assert_eq!(bindings.buttons(Action::Fire), &[Button::B]);
+        // the buttons of actions listed without any are kept
+        assert_eq!(bindings.keys(Action::Pause), &[Keycode::P]);
+        assert_eq!(bindings.buttons(Action::Pause), &[Button::Start]);
+        // and so is everything about those which are not listed
+        assert_eq!(bindings.keys(Action::MoveUp), &[Keycode::Up]);
+    }
+
+    #[test]
+    fn survives_writing_out() {
+        let mut bindings = Bindings::default();
+        bindings.set(Action::MoveUp, vec![Keycode::W, Keycode::Up]);
+        bindings.set(Action::Fire, vec![Keycode::Comma, Keycode::Space]);
+        bindings.set(Action::SelectWeapon(2), vec![]);
+        bindings.set_buttons(Action::Fire, vec![Button::X, Button::LeftShoulder]);
+        bindings.deadzone = 0.1;
+
+        let config = bindings.to_config();
+        assert!(config.contains("fire = Comma, Space, pad:x, pad:leftshoulder\n"));
+        let parsed = Bindings::parse(&config, "test.cfg").unwrap();
+        assert_eq!(parsed.to_config(), config);
+        for action in Action::all() {
+            assert_eq!(parsed.keys(action), bindings.keys(action));
+            assert_eq!(parsed.buttons(action), bindings.buttons(action));
+        }
+        assert_eq!(parsed.deadzone, 0.1);
+        assert_eq!(Bindings::parse(&Bindings::default().to_config(), "test.cfg").unwrap().deadzone,
+                   DEFAULT_DEADZONE);
+    }
+
+    #[test]
+    fn rejects_deadzones_outside_the_stick() {
+        for deadzone in &["-0.1", "1", "1.5", "NaN", "inf", "half"] {
+            let config = format!("fire = Space\ndeadzone = {}\n", deadzone);
+            assert_eq!(Bindings::parse(&config, "test.cfg").err(),
+                       Some("test.cfg:2: the deadzone must be between 0 and 1".to_string()));
+        }
+        assert_eq!(Bindings::parse("deadzone = 0", "test.cfg").unwrap().deadzone, 0.0);
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        assert_eq!(Bindings::parse("\nfire = Nope", "test.cfg").err(),
+                   Some("test.cfg:2: unknown key `Nope`".to_string()));
+        assert_eq!(Bindings::parse("fire = pad:nope", "test.cfg").err(),
+                   Some("test.cfg:1: unknown button `pad:nope`".to_string()));
+        assert_eq!(Bindings::parse("jump = Space", "test.cfg").err(),
+                   Some("test.cfg:1: unknown action `jump`".to_string()));
+        assert_eq!(Bindings::parse("fire", "test.cfg").err(),
+                   Some("test.cfg:1: expected `action = keys`".to_string()));
+    }
+}
@@ -0,0 +1,491 @@
+use ::phi::{Error, Phi};
+use ::phi::data::{Circle, Polygon, Rectangle, Shape};
+use ::phi::gfx::{AnimatedSprite, AnimatedSpriteDescr, Sprite};
+use ::rustc_serialize::json::Json;
+use ::std::collections::HashMap;
+use ::std::fs::File;
+use ::std::io::Read;
+
+/// Where the sprites of the game are described.
+pub const SPRITES_PATH: &'static str = "assets/sprites.json";
+
+/// An image cut into a grid of frames of the same size.
+struct Sheet {
+    image: String,
+    frame_w: f64,
+    frame_h: f64,
+    frames_wide: usize,
+    frames_high: usize,
+    total_frames: usize,
+    /// The part of every frame which collides with other things, relative
+    /// to its top left corner.
+    hitbox: Rectangle,
+    /// The hitbox of every frame, where they differ.
+    hitboxes: Option<Vec<Rectangle>>,
+    shape: SheetShape,
+}
+
+/// The exact shape of the frames of a sheet, where the hitbox is too rough.
+enum SheetShape {
+    Hitbox,
+    Circle(Circle),
+    Polygon(Polygon),
+    /// The opaque pixels of every frame.
+    Mask,
+}
+
+/// How long the frames of an animation are shown.
+enum Timing {
+    Fps(f64),
+    /// The duration of every frame, in seconds.
+    Durations(Vec<f64>),
+}
+
+/// A run of consecutive frames from a sheet.
+struct Animation {
+    sheet: String,
+    first: usize,
+    count: usize,
+    timing: Timing,
+    /// The hitbox of every frame of the animation, where they differ from
+    /// those of the sheet.
+    hitboxes: Option<Vec<Rectangle>>,
+}
+
+/// Describes how the images in `assets/` are laid out, so that the art can
+/// change without touching the code. A manifest is a JSON file such as:
+///
+/// ```json
+/// {
+///     "sheets": {
+///         "explosion": {
+///             "image": "assets/explosion.png",
+///             "frame_w": 96, "frame_h": 96,
+///             "frames_wide": 5, "frames_high": 4, "total_frames": 17,
+///             "hitbox": { "x": 8, "y": 8, "w": 80, "h": 80 }
+///         },
+///         "ship": {
+///             "image": "assets/ship.png",
+///             "frame_w": 40, "frame_h": 30,
+///             "shape": { "polygon": [[0, 0], [40, 15], [0, 30]] }
+///         }
+///     },
+///     "animations": {
+///         "explosion": { "sheet": "explosion", "fps": 16 },
+///         "flash": { "sheet": "explosion", "first": 2, "count": 3,
+///                    "durations": [0.05, 0.1, 0.05],
+///                    "hitboxes": [{ "x": 40, "y": 40, "w": 16, "h": 16 },
+///                                 { "x": 24, "y": 24, "w": 48, "h": 48 },
+///                                 { "x": 40, "y": 40, "w": 16, "h": 16 }] }
+///     }
+/// }
+/// ```
+///
+/// `total_frames` defaults to the whole grid, `hitbox` to the whole frame,
+/// and an animation to every frame of its sheet. Sheets and animations can
+/// also list a hitbox for every one of their frames as `hitboxes`; without
+/// them, an animation uses those of its sheet, and a sheet its `hitbox`.
+/// The `shape` of a sheet is either `"mask"`, for the opaque pixels of
+/// every frame, a `circle` with an `x`, a `y` and an `r`, or a convex
+/// `polygon`, and defaults to the hitbox.
+pub struct Manifest {
+    sheets: HashMap<String, Sheet>,
+    animations: HashMap<String, Animation>,
+}
+
+impl Manifest {
+    pub fn load(path: &str) -> Result<Manifest, Error> {
+        let mut contents = String::new();
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|e| Error::Asset(format!("Could not read {}: {}", path, e))));
+
+        Manifest::parse(&contents, path)
+    }
+
+    /// Parses a manifest in the format described above. `origin` is only
+    /// used to make error messages point to the right place.
+    pub fn parse(contents: &str, origin: &str) -> Result<Manifest, Error> {
+        let json = try!(Json::from_str(contents)
+            .map_err(|e| Error::Asset(format!("{}: {}", origin, e))));
+
+        let mut manifest = Manifest {
+            sheets: HashMap::new(),
+            animations: HashMap::new(),
+        };
+
+        let sheets = try!(json.find("sheets").and_then(Json::as_object)
+            .ok_or(Error::Asset(format!("{}: expected a `sheets` object", origin))));
+        for (name, sheet) in sheets {
+            let sheet = try!(parse_sheet(sheet)
+                .map_err(|e| Error::Asset(format!("{}: sheet `{}`: {}", origin, name, e))));
+            manifest.sheets.insert(name.clone(), sheet);
+        }
+
+        if let Some(animations) = json.find("animations") {
+            let animations = try!(animations.as_object()
+                .ok_or(Error::Asset(format!("{}: `animations` should be an object", origin))));
+            for (name, animation) in animations {
+                let animation = try!(manifest.parse_animation(animation)
+                    .map_err(|e| Error::Asset(format!("{}: animation `{}`: {}", origin, name, e))));
+                manifest.animations.insert(name.clone(), animation);
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Every frame of the sheet called `name`, in reading order.
+    pub fn frames(&self, phi: &mut Phi, name: &str) -> Result<Vec<Sprite>, Error> {
+        let sheet = try!(self.sheet(name).map_err(Error::Asset));
+        AnimatedSprite::load_frames(phi, AnimatedSpriteDescr {
+            image_path: &sheet.image,
+            total_frames: sheet.total_frames,
+            frames_high: sheet.frames_high,
+            frames_wide: sheet.frames_wide,
+            frame_w: sheet.frame_w,
+            frame_h: sheet.frame_h,
+        })
+    }
+
+    pub fn animation(&self, phi: &mut Phi, name: &str) -> Result<AnimatedSprite, Error> {
+        let animation = try!(self.animations.get(name)
+            .ok_or(Error::Asset(format!("Unknown animation `{}`", name))));
+
+        let frames = try!(self.frames(phi, &animation.sheet)).into_iter()
+            .skip(animation.first)
+            .take(animation.count)
+            .collect();
+
+        Ok(match animation.timing {
+            Timing::Fps(fps) => AnimatedSprite::with_fps(frames, fps),
+            Timing::Durations(ref durations) => AnimatedSprite::with_durations(frames, durations.clone()),
+        })
+    }
+
+    /// The `hitbox` of the sheet or of the sheet of the animation called
+    /// `name`, relative to the top left corner of its frames, regardless of
+    /// their own `hitboxes`.
+    pub fn hitbox(&self, name: &str) -> Result<Rectangle, Error> {
+        let sheet = match self.animations.get(name) {
+            Some(animation) => &animation.sheet[..],
+            None => name,
+        };
+        self.sheet(sheet).map(|sheet| sheet.hitbox).map_err(Error::Asset)
+    }
+
+    /// The hitbox of every frame of the sheet or the animation called
+    /// `name`, relative to their top left corner.
+    pub fn hitboxes(&self, name: &str) -> Result<Vec<Rectangle>, Error> {
+        let (sheet, first, count) = try!(self.frames_of(name).map_err(Error::Asset));
+        if let Some(hitboxes) = self.animations.get(name).and_then(|animation| animation.hitboxes.as_ref()) {
+            return Ok(hitboxes.clone());
+        }
+
+        Ok(match sheet.hitboxes {
+            Some(ref hitboxes) => hitboxes[first..first + count].to_vec(),
+            None => vec![sheet.hitbox; count],
+        })
+    }
+
+    /// The shape of every frame of the sheet or the animation called `name`,
+    /// relative to their top left corner. Masks are made from the image
+    /// once and for all.
+    pub fn shapes(&self, phi: &mut Phi, name: &str) -> Result<Vec<Shape>, Error> {
+        let (sheet, first, count) = try!(self.frames_of(name).map_err(Error::Asset));
+
+        let shape = match sheet.shape {
+            SheetShape::Hitbox => {
+                return self.hitboxes(name).map(|hitboxes| hitboxes.into_iter().map(Shape::Rect).collect());
+            },
+            SheetShape::Circle(circle) => Shape::Circle(circle),
+            SheetShape::Polygon(ref polygon) => Shape::Polygon(polygon.clone()),
+            SheetShape::Mask => {
+                let regions: Vec<Rectangle> = (first..first + count).map(|i| Rectangle {
+                    x: sheet.frame_w * (i % sheet.frames_wide) as f64,
+                    y: sheet.frame_h * (i / sheet.frames_wide) as f64,
+                    w: sheet.frame_w,
+                    h: sheet.frame_h,
+                }).collect();
+                let masks = try!(phi.assets.masks(&sheet.image, &regions));
+                return Ok(masks.into_iter().map(Shape::Mask).collect());
+            },
+        };
+        Ok(vec![shape; count])
+    }
+
+    fn sheet(&self, name: &str) -> Result<&Sheet, String> {
+        self.sheets.get(name).ok_or(format!("Unknown sprite sheet `{}`", name))
+    }
+
+    /// The sheet of the sheet or the animation called `name`, along with
+    /// the first of its frames which are used and how many of them.
+    fn frames_of(&self, name: &str) -> Result<(&Sheet, usize, usize), String> {
+        match self.animations.get(name) {
+            Some(animation) => self.sheet(&animation.sheet).map(|sheet| (sheet, animation.first, animation.count)),
+            None => self.sheet(name).map(|sheet| (sheet, 0, sheet.total_frames)),
+        }
+    }
+
+    fn parse_animation(&self, json: &Json) -> Result<Animation, String> {
+        let sheet_name = try!(json.find("sheet").and_then(Json::as_string)
+            .ok_or("`sheet` should be the name of a sheet".to_string()));
+        let sheet = try!(self.sheet(sheet_name));
+
+        let first = try!(number(json, "first", Some(0.0))) as usize;
+        let count = try!(number(json, "count", Some(sheet.total_frames as f64 - first as f64))) as usize;
+        if count == 0 || first + count > sheet.total_frames {
+            return Err(format!("frames {} to {} are not all in the sheet", first, first + count));
+        }
+
+        let timing = match json.find("durations") {
+            Some(durations) => {
+                let durations: Vec<f64> = try!(durations.as_array()
+                    .and_then(|durations| durations.iter().map(Json::as_f64).collect())
+                    .ok_or("`durations` should be a list of numbers".to_string()));
+                if durations.len() != count || durations.iter().any(|&d| d <= 0.0) {
+                    return Err(format!("expected {} positive durations", count));
+                }
+                Timing::Durations(durations)
+            },
+            None => {
+                let fps = try!(number(json, "fps", None));
+                if fps <= 0.0 {
+                    return Err("`fps` should be positive".to_string());
+                }
+                Timing::Fps(fps)
+            },
+        };
+
+        Ok(Animation {
+            sheet: sheet_name.to_string(),
+            first: first,
+            count: count,
+            timing: timing,
+            hitboxes: try!(parse_hitboxes(json, count)),
+        })
+    }
+}
+
+
+fn parse_sheet(json: &Json) -> Result<Sheet, String> {
+    let image = try!(json.find("image").and_then(Json::as_string)
+        .ok_or("`image` should be the path of an image".to_string()));
+
+    let frame_w = try!(number(json, "frame_w", None));
+    let frame_h = try!(number(json, "frame_h", None));
+    if frame_w <= 0.0 || frame_h <= 0.0 {
+        return Err("`frame_w` and `frame_h` should be positive".to_string());
+    }
+    let frames_wide = try!(number(json, "frames_wide", Some(1.0))) as usize;
+    let frames_high = try!(number(json, "frames_high", Some(1.0))) as usize;
+    let total_frames = try!(number(json, "total_frames", Some((frames_wide * frames_high) as f64))) as usize;
+
+    if total_frames == 0 || total_frames > frames_wide * frames_high {
+        return Err(format!("{} frames do not fit in a {}x{} grid", total_frames, frames_wide, frames_high));
+    }
+
+    let hitbox = match json.find("hitbox") {
+        Some(hitbox) => try!(parse_hitbox(hitbox)),
+        None => Rectangle::with_size(frame_w, frame_h),
+    };
+
+    let shape = match json.find("shape") {
+        None => SheetShape::Hitbox,
+        Some(shape) => try!(parse_shape(shape)),
+    };
+
+    Ok(Sheet {
+        image: image.to_string(),
+        frame_w: frame_w,
+        frame_h: frame_h,
+        frames_wide: frames_wide,
+        frames_high: frames_high,
+        total_frames: total_frames,
+        hitbox: hitbox,
+        hitboxes: try!(parse_hitboxes(json, total_frames)),
+        shape: shape,
+    })
+}
+
+fn parse_hitbox(json: &Json) -> Result<Rectangle, String> {
+    Ok(Rectangle {
+        x: try!(number(json, "x", Some(0.0))),
+        y: try!(number(json, "y", Some(0.0))),
+        w: try!(number(json, "w", None)),
+        h: try!(number(json, "h", None)),
+    })
+}
+
+/// The `hitboxes` listed in `json`, if any, which should be one per frame
+/// for `count` frames.
+fn parse_hitboxes(json: &Json, count: usize) -> Result<Option<Vec<Rectangle>>, String> {
+    let hitboxes = match json.find("hitboxes") {
+        Some(hitboxes) => try!(hitboxes.as_array()
+            .ok_or("`hitboxes` should be a list of hitboxes".to_string())),
+        None => return Ok(None),
+    };
+
+    if hitboxes.len() != count {
+        return Err(format!("expected {} hitboxes, one per frame", count));
+    }
+    hitboxes.iter().map(parse_hitbox).collect::<Result<Vec<_>, _>>().map(Some)
+}
+
+fn parse_shape(json: &Json) -> Result<SheetShape, String> {
+    if json.as_string() == Some("mask") {
+        return Ok(SheetShape::Mask);
+    }
+
+    if let Some(circle) = json.find("circle") {
+        let r = try!(number(circle, "r", None));
+        if r <= 0.0 {
+            return Err("the radius of a circle should be positive".to_string());
+        }
+        return Ok(SheetShape::Circle(Circle {
+            x: try!(number(circle, "x", None)),
+            y: try!(number(circle, "y", None)),
+            r: r,
+        }));
+    }
+
+    if let Some(points) = json.find("polygon") {
+        let points: Vec<(f64, f64)> = try!(points.as_array()
+            .and_then(|points| points.iter().map(|point| {
+                let point = match point.as_array() {
+                    Some(point) if point.len() == 2 => point,
+                    _ => return None,
+                };
+                point[0].as_f64().and_then(|x| point[1].as_f64().map(|y| (x, y)))
+            }).collect())
+            .ok_or("`polygon` should be a list of `[x, y]` points".to_string()));
+        return Polygon::new(points)
+            .map(SheetShape::Polygon)
+            .ok_or("`polygon` should have at least 3 points, going around a convex polygon".to_string());
+    }
+
+    Err("`shape` should be \"mask\", a `circle` or a `polygon`".to_string())
+}
+
+/// The number called `key` in `json`, or `default` if there is none.
+fn number(json: &Json, key: &str, default: Option<f64>) -> Result<f64, String> {
+    match json.find(key) {
+        Some(value) => value.as_f64().ok_or(format!("`{}` should be a number", key)),
+        None => default.ok_or(format!("`{}` is missing", key)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ::phi::Error;
+    use ::phi::data::Rectangle;
+    use super::{Manifest, SheetShape, Timing};
+
+    fn rect(x: f64, y: f64, w: f64, h: f64) -> Rectangle {
+        Rectangle { x: x, y: y, w: w, h: h }
+    }
+
+    /// The message of the error `contents` fail to parse with.
+    fn error(contents: &str) -> String {
+        match Manifest::parse(contents, "test.json") {
+            Err(Error::Asset(message)) => message,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("parsed {}", contents),
+        }
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let manifest = Manifest::parse(r#"{
+            "sheets": {
+                "ship": { "image": "ship.png", "frame_w": 40, "frame_h": 30 },
+                "boom": { "image": "boom.png", "frame_w": 10, "frame_h": 10,
+                          "frames_wide": 3, "frames_high": 2 }
+            },
+            "animations": { "boom": { "sheet": "boom", "fps": 10 } }
+        }"#, "test.json").unwrap();
+
+        let ship = &manifest.sheets["ship"];
+        assert_eq!((ship.frames_wide, ship.frames_high, ship.total_frames), (1, 1, 1));
+        assert_eq!(ship.hitbox, rect(0.0, 0.0, 40.0, 30.0));
+        assert!(match ship.shape { SheetShape::Hitbox => true, _ => false });
+
+        assert_eq!(manifest.sheets["boom"].total_frames, 6);
+        let boom = &manifest.animations["boom"];
+        assert_eq!((boom.first, boom.count), (0, 6));
+        assert!(match boom.timing { Timing::Fps(fps) => fps == 10.0, _ => false });
+        assert_eq!(manifest.hitboxes("boom").unwrap(), vec![rect(0.0, 0.0, 10.0, 10.0); 6]);
+    }
+
+    #[test]
+    fn frames_can_have_their_own_hitboxes() {
+        let manifest = Manifest::parse(r#"{
+            "sheets": {
+                "boom": { "image": "boom.png", "frame_w": 10, "frame_h": 10, "frames_wide": 3,
+                          "hitboxes": [{ "w": 1, "h": 1 }, { "w": 2, "h": 2 }, { "x": 1, "w": 3, "h": 3 }] },
+                "ship": { "image": "ship.png", "frame_w": 10, "frame_h": 10, "frames_wide": 2,
+                          "hitbox": { "x": 1, "y": 1, "w": 8, "h": 8 } }
+            },
+            "animations": {
+                "end": { "sheet": "boom", "first": 1, "fps": 10 },
+                "flash": { "sheet": "boom", "first": 1, "count": 1, "fps": 10,
+                           "hitboxes": [{ "w": 9, "h": 9 }] },
+                "ship": { "sheet": "ship", "fps": 10 }
+            }
+        }"#, "test.json").unwrap();
+
+        assert_eq!(manifest.hitboxes("boom").unwrap(),
+                   vec![rect(0.0, 0.0, 1.0, 1.0), rect(0.0, 0.0, 2.0, 2.0), rect(1.0, 0.0, 3.0, 3.0)]);
+        assert_eq!(manifest.hitboxes("end").unwrap(),
+                   vec![rect(0.0, 0.0, 2.0, 2.0), rect(1.0, 0.0, 3.0, 3.0)]);
+        assert_eq!(manifest.hitboxes("flash").unwrap(), vec![rect(0.0, 0.0, 9.0, 9.0)]);
+        assert_eq!(manifest.hitboxes("ship").unwrap(), vec![rect(1.0, 1.0, 8.0, 8.0); 2]);
+        // the single hitbox is still the whole frame
+        assert_eq!(manifest.hitbox("boom").unwrap(), rect(0.0, 0.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn rejects_malformed_manifests() {
+        assert!(error("{ \"sheets\": ").starts_with("test.json: "));
+        assert_eq!(error("{}"), "test.json: expected a `sheets` object");
+        assert_eq!(error(r#"{ "sheets": { "a": { "image": "a.png", "frame_h": 1 } } }"#),
+                   "test.json: sheet `a`: `frame_w` is missing");
+        assert_eq!(error(r#"{ "sheets": { "a": { "image": "a.png", "frame_w": "wide", "frame_h": 1 } } }"#),
+                   "test.json: sheet `a`: `frame_w` should be a number");
+        assert_eq!(error(r#"{ "sheets": { "a": { "image": "a.png", "frame_w": 0, "frame_h": 1 } } }"#),
+                   "test.json: sheet `a`: `frame_w` and `frame_h` should be positive");
+        assert_eq!(error(r#"{ "sheets": { "a": { "image": "a.png", "frame_w": 1, "frame_h": -1 } } }"#),
+                   "test.json: sheet `a`: `frame_w` and `frame_h` should be positive");
+        assert_eq!(error(r#"{ "sheets": { "a": { "image": "a.png", "frame_w": 1, "frame_h": 1,
+                                                 "total_frames": 2 } } }"#),
+                   "test.json: sheet `a`: 2 frames do not fit in a 1x1 grid");
+        assert_eq!(error(r#"{ "sheets": { "a": { "image": "a.png", "frame_w": 1, "frame_h": 1,
+                                                 "shape": { "polygon": [[0, 0], [2, 0], [1, 1], [2, 2], [0, 2]] } } } }"#),
+                   "test.json: sheet `a`: `polygon` should have at least 3 points, going around a convex polygon");
+        assert_eq!(error(r#"{ "sheets": { "a": { "image": "a.png", "frame_w": 1, "frame_h": 1,
+                                                 "hitboxes": [] } } }"#),
+                   "test.json: sheet `a`: expected 1 hitboxes, one per frame");
+        assert_eq!(error(r#"{ "sheets": {}, "animations": { "b": { "sheet": "a", "fps": 1 } } }"#),
+                   "test.json: animation `b`: Unknown sprite sheet `a`");
+    }
+
+    #[test]
+    fn rejects_malformed_animations() {
+        let manifest = |animation: &str| format!(r#"{{
+            "sheets": {{ "a": {{ "image": "a.png", "frame_w": 1, "frame_h": 1, "frames_wide": 4 }} }},
+            "animations": {{ "b": {} }}
+        }}"#, animation);
+
+        assert_eq!(error(&manifest(r#"{ "sheet": "a", "first": 3, "count": 2, "fps": 1 }"#)),
+                   "test.json: animation `b`: frames 3 to 5 are not all in the sheet");
+        assert_eq!(error(&manifest(r#"{ "sheet": "a", "fps": 0 }"#)),
+                   "test.json: animation `b`: `fps` should be positive");
+        assert_eq!(error(&manifest(r#"{ "sheet": "a" }"#)),
+                   "test.json: animation `b`: `fps` is missing");
+        assert_eq!(error(&manifest(r#"{ "sheet": "a", "durations": [1, 1] }"#)),
+                   "test.json: animation `b`: expected 4 positive durations");
+        assert_eq!(error(&manifest(r#"{ "sheet": "a", "fps": 1, "hitboxes": [{ "w": 1 }, {}, {}, {}] }"#)),
+                   "test.json: animation `b`: `h` is missing");
+    }
+}
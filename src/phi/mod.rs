@@ -1,13 +1,33 @@
-use ::sdl2::render::Renderer;
 use ::std::collections::HashMap;
 use ::sdl2::pixels::Color;
 use ::std::path::Path;
+use ::phi::assets::Assets;
+use ::phi::audio::{Audio, Volume};
+use ::phi::backend::{Backend, DrawCall, SdlBackend, HeadlessBackend};
 use ::phi::gfx::Sprite;
+use ::phi::stack::ViewStack;
+use ::phi::rng::Rng;
+
+pub use ::phi::error::Error;
 
 #[macro_use]
 mod events;
+pub mod assets;
+pub mod audio;
+pub mod backend;
 pub mod data;
+pub mod ecs;
+pub mod error;
 pub mod gfx;
+pub mod input;
+pub mod manifest;
+pub mod pool;
+pub mod replay;
+pub mod rng;
+pub mod stack;
+pub mod storage;
+#[cfg(test)]
+pub mod testing;
 
 struct_events! {
     keyboard: {
@@ -26,48 +46,112 @@ struct_events! {
         key_3: Num3
     },
     else: {
-        quit: Quit { .. }
+        quit: Quit { .. },
+        focus_lost: Window { win_event_id: ::sdl2::event::WindowEventId::FocusLost, .. }
     }
 }
 
 /// Bundles the Phi abstractions in a single strucuture.
 pub struct Phi<'window> {
     pub events: Events,
-    pub renderer: Renderer<'window>,
+    pub renderer: Box<Backend + 'window>,
+    /// The only source of randomness the game should use, so that a run
+    /// can be replayed from its seed.
+    pub rng: Rng,
+    pub assets: Assets,
+    pub audio: Audio,
 
     cached_fonts: HashMap<(&'static str, i32), ::sdl2_ttf::Font>,
+    /// How many times a font was reloaded.
+    font_generation: u32,
 }
 
 impl <'window> Phi<'window> {
 
-    fn new(events: Events, renderer: Renderer<'window>) -> Phi<'window> {
+    fn new(events: Events, renderer: Box<Backend + 'window>) -> Phi<'window> {
         ::sdl2_image::init(::sdl2_image::INIT_PNG);
         Phi {
             events: events,
             renderer: renderer,
+            rng: Rng::from_entropy(),
+            assets: Assets::new(),
+            audio: Audio::silent(),
             cached_fonts: HashMap::new(),
+            font_generation: 0,
         }
     }
 
+    /// Creates a context which renders into an offscreen buffer of the given
+    /// size rather than a window, so views can be driven frame by frame
+    /// without a display. The draw calls of the last presented frame are
+    /// available through `renderer.last_frame()`.
+    pub fn headless(sdl_context: &::sdl2::Sdl, w: u32, h: u32) -> Result<Phi<'static>, Error> {
+        let pump = try!(sdl_context.event_pump().map_err(Error::Sdl));
+        let backend = try!(HeadlessBackend::new(w, h));
+        Ok(Phi::new(Events::new(pump), Box::new(backend)))
+    }
+
     pub fn output_size(&self) -> (f64, f64) {
-        let (w, h) = self.renderer.output_size().unwrap();
-        (w as f64, h as f64)
+        self.renderer.output_size()
+    }
+
+    /// The whole image at `path`, loaded through `assets`.
+    pub fn sprite(&mut self, path: &str) -> Result<Sprite, Error> {
+        self.assets.sprite(&*self.renderer, path)
     }
 
-    pub fn ttf_str_sprite(&mut self, text: &str, font_path: &'static str, size: i32, color: Color) -> Option<Sprite> {
+    pub fn ttf_str_sprite(&mut self, text: &str, font_path: &'static str, size: i32, color: Color) -> Result<Sprite, Error> {
         // if it exists, make a texture
         if let Some(font) = self.cached_fonts.get(&(font_path, size)) {
-            return font.render(text, ::sdl2_ttf::blended(color)).ok()
-                .and_then(|surface| self.renderer.create_texture_from_surface(&surface).ok())
+            return font.render(text, ::sdl2_ttf::blended(color))
+                .and_then(|surface| self.renderer.texture_from_surface(&surface))
                 .map(Sprite::new)
+                .map_err(|e| Error::Font(format!("Could not render `{}`: {}", text, e)))
         }
 
         // otherwise find it from the path, and recurse
-        ::sdl2_ttf::Font::from_file(Path::new(font_path), size).ok()
-            .and_then(|font| {
-                self.cached_fonts.insert((font_path, size), font);
-                self.ttf_str_sprite(text, font_path, size, color)
-            })
+        let font = try!(::sdl2_ttf::Font::from_file(Path::new(font_path), size)
+            .map_err(|e| Error::Font(format!("Could not load {}: {}", font_path, e))));
+        self.assets.watch(font_path);
+        self.cached_fonts.insert((font_path, size), font);
+        self.ttf_str_sprite(text, font_path, size, color)
+    }
+
+    /// Changes whenever a font is reloaded, so that the text which was
+    /// rendered with the old version can be rendered again.
+    pub fn font_generation(&self) -> u32 {
+        self.font_generation
+    }
+
+    /// Plays `replay` back instead of listening to the player. The replay
+    /// brings the seed and the controls it was recorded with.
+    pub fn play(&mut self, replay: Replay) {
+        self.rng = Rng::new(replay.seed);
+        self.events.bindings = replay.bindings.clone();
+        self.events.play(replay);
+    }
+
+    /// Swaps in the new version of the assets which changed on disk, if
+    /// `assets.hot_reload` is enabled. `dt` is the time elapsed since the
+    /// last call.
+    pub fn reload_assets(&mut self, dt: f64) {
+        if !self.assets.hot_reload {
+            return;
+        }
+
+        for path in self.assets.changed(dt) {
+            // fonts are loaded again the next time some text is rendered
+            let fonts = self.cached_fonts.len();
+            self.cached_fonts.retain(|&(font_path, _), _| font_path != path);
+            if self.cached_fonts.len() != fonts {
+                self.font_generation += 1;
+            }
+
+            match self.assets.reload(&*self.renderer, &path) {
+                Ok(()) => println!("Reloaded {}", path),
+                Err(e) => println!("{}", e),
+            }
+        }
     }
 
 }
@@ -84,75 +168,260 @@ impl <'window> Drop for Phi<'window> {
 pub enum ViewAction {
     None,
     Quit,
-    ChangeView(Box<View>),
+    /// Pauses the current view and puts a new one on top of it.
+    Push(Box<View>),
+    /// Removes the current view, resuming the one below it.
+    Pop,
+    /// Swaps the current view for a new one.
+    Replace(Box<View>),
+    /// Removes every view and starts over with a new one.
+    Reset(Box<View>),
 }
 
 pub trait View {
-    /// Called on every frame to take care of both the logic and the
-    /// rendering of the current view.
+    /// Called at a fixed rate to take care of the logic of the current view.
+    ///
+    /// `dt` is the duration of a tick, expressed in seconds.
+    fn update(&mut self, context: &mut Phi, dt: f64) -> ViewAction;
+
+    /// Called on every frame to draw the current view.
     ///
-    /// `elapsed is expressed in seconds.
-    fn render(&mut self, context: &mut Phi, elapsed: f64) -> ViewAction;
+    /// `alpha`, between 0 and 1, is how far we are between the last tick
+    /// and the next one, so that moving things can be interpolated.
+    fn render(&mut self, context: &mut Phi, alpha: f64);
+
+    /// Called when the view is put on the stack.
+    fn on_enter(&mut self, _context: &mut Phi) {}
+
+    /// Called when another view is pushed on top of this one.
+    fn on_pause(&mut self, _context: &mut Phi) {}
+
+    /// Called when this view is on top of the stack again.
+    fn on_resume(&mut self, _context: &mut Phi) {}
+
+    /// Called when the view is removed from the stack.
+    fn on_exit(&mut self, _context: &mut Phi) {}
+
+    /// Whether the view is drawn over the one below it, for example a menu
+    /// over a paused game, rather than covering the whole window.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// Frames which took longer than this (in seconds), for example because
+/// the window was being dragged around, are not fully caught up on.
+const MAX_FRAME_TIME: f64 = 0.25;
+
+/// How the game should be run, usually built from the command line.
+pub struct Config {
+    pub title: String,
+    /// How many times per second the views are updated, independently of
+    /// how often they are rendered.
+    pub tick_rate: f64,
+    /// When set, runs this many ticks offscreen instead of opening a window.
+    pub headless: Option<u32>,
+    /// How many frames per second may be drawn at most. The frames are
+    /// synchronized with the display anyway, so this only matters where
+    /// vsync is not available.
+    pub max_fps: Option<u32>,
+    /// Where to save the inputs of this run, so that it can be replayed.
+    pub record: Option<String>,
+    /// A recording to play back instead of listening to the player.
+    pub replay: Option<String>,
+    /// The seed of `Phi::rng`. An arbitrary one is used if it is not set,
+    /// and a replay always brings its own.
+    pub seed: Option<u32>,
+    /// Whether to reload the assets when they change, during development.
+    pub hot_reload: bool,
+    /// The volumes of the sound, between 0 and 1.
+    pub volume: f64,
+    pub sfx_volume: f64,
+    pub music_volume: f64,
+}
+
+impl Config {
+    pub fn new(title: &str) -> Config {
+        Config {
+            title: title.to_string(),
+            tick_rate: 60.0,
+            headless: None,
+            max_fps: None,
+            record: None,
+            replay: None,
+            seed: None,
+            hot_reload: false,
+            volume: 1.0,
+            sfx_volume: 1.0,
+            music_volume: 1.0,
+        }
+    }
 }
 
-pub fn spawn<F>(title: &str, init: F)
-where F: Fn(&mut Phi) -> Box<View> {
+/// Runs the game loop until the views quit, either in a window or, if
+/// `config.headless` is set, offscreen. Fails if the game could not be
+/// started at all; the views handle the errors which happen later on.
+pub fn spawn<F>(config: Config, init: F) -> Result<(), Error>
+where F: Fn(&mut Phi) -> Result<Box<View>, Error> {
+    // there is no need to hear anything when running offscreen, but the
+    // sounds still get mixed
+    if config.headless.is_some() && ::std::env::var_os("SDL_AUDIODRIVER").is_none() {
+        ::std::env::set_var("SDL_AUDIODRIVER", "dummy");
+    }
+
     // init
-    let sdl_context = ::sdl2::init().unwrap();
-    let video = sdl_context.video().unwrap();
-    let mut timer = sdl_context.timer().unwrap();
-    let _ttf_context = ::sdl2_ttf::init();
+    let sdl_context = try!(::sdl2::init().map_err(Error::Sdl));
+    let _ttf_context = try!(::sdl2_ttf::init().map_err(Error::Sdl));
+
+    let replay = match config.replay {
+        Some(ref path) => Some(try!(Replay::load(path).map_err(Error::Asset))),
+        None => None,
+    };
+
+    // A replay only plays out the same way in a window of the size it was
+    // recorded with, which is why the window cannot be resized either.
+    let (w, h) = replay.as_ref().map(|replay| replay.size).unwrap_or((800, 600));
+    let fixed_size = replay.is_some() || config.record.is_some();
+
+    if let Some(ticks) = config.headless {
+        let mut context = try!(Phi::headless(&sdl_context, w, h));
+        enable_audio(&sdl_context, &mut context);
+        prepare(&mut context, &config, replay, (w, h));
+        try!(run_headless(&mut context, ticks, config.tick_rate, init));
+        finish(&mut context, &config);
+        return Ok(());
+    }
+
+    let video = try!(sdl_context.video().map_err(Error::Sdl));
+    let mut timer = try!(sdl_context.timer().map_err(Error::Sdl));
 
     // window
-    let window = video.window(title, 800, 600)
-        .position_centered().opengl().resizable()
-        .build().unwrap();
+    let mut builder = video.window(&config.title, w, h);
+    builder.position_centered().opengl();
+    if !fixed_size {
+        builder.resizable();
+    }
+    let window = try!(builder.build().map_err(Error::Sdl));
 
     //
-    let mut context = Phi::new(
-        Events::new(sdl_context.event_pump().unwrap()),
-        window.renderer().accelerated().build().unwrap());
+    let pump = try!(sdl_context.event_pump().map_err(Error::Sdl));
+    let renderer = try!(window.renderer().accelerated().present_vsync().build().map_err(Error::Sdl));
+    let mut context = Phi::new(Events::new(pump), Box::new(SdlBackend::new(renderer)));
+
+    match sdl_context.game_controller() {
+        Ok(subsystem) => context.events.enable_controllers(subsystem),
+        Err(e) => println!("Game controllers are disabled: {}", e),
+    }
+    enable_audio(&sdl_context, &mut context);
+
+    prepare(&mut context, &config, replay, (w, h));
 
     //
-    let mut current_view = init(&mut context);
+    let root = try!(init(&mut context));
+    let mut views = ViewStack::new(&mut context, root);
 
     // timing
-    let interval = 1_000 / 60;
+    let tick = 1.0 / config.tick_rate;
     let mut before = timer.ticks();
-    let mut last_second = timer.ticks();
-    let mut fps = 0u16;
+    let mut accumulator = 0.0;
 
-    loop {
+    'running: loop {
 
         let now = timer.ticks();
-        let dt = now - before;
-        let elapsed = dt as f64 / 1_000.0;
-
-        if dt < interval {
-            timer.delay(interval - dt);
-            continue;
-        }
-
+        let elapsed = (now - before) as f64 / 1_000.0;
         before = now;
-        fps += 1;
 
-        if now - last_second > 1_000 {
-            println!("FPS: {}", fps);
-            last_second = now;
-            fps = 0;
+        // LOGIC
+
+        accumulator += elapsed.min(MAX_FRAME_TIME);
+        while accumulator >= tick {
+            context.events.pump(&*context.renderer);
+            if !views.update(&mut context, tick) {
+                break 'running;
+            }
+            accumulator -= tick;
         }
 
+        context.reload_assets(elapsed);
+
         // RENDERING
 
-        context.events.pump(&mut context.renderer);
-        match current_view.render(&mut context, elapsed) {
-            ViewAction::None => context.renderer.present(),
-            ViewAction::Quit => break,
-            ViewAction::ChangeView(new_view) => {
-                println!("changeView.before");
-                current_view = new_view;
-                println!("changeView.after");
+        views.render(&mut context, accumulator / tick);
+
+        // sleeps for what is left of the frame, if it is capped
+        if let Some(fps) = config.max_fps {
+            let frame = 1_000 / fps.max(1);
+            let spent = timer.ticks() - now;
+            if spent < frame {
+                timer.delay(frame - spent);
             }
         }
     }
+
+    finish(&mut context, &config);
+    Ok(())
+}
+
+/// Plays sounds through SDL, if it can.
+fn enable_audio(sdl_context: &::sdl2::Sdl, context: &mut Phi) {
+    match sdl_context.audio().map_err(Error::Sdl).and_then(Audio::open) {
+        Ok(audio) => context.audio = audio,
+        Err(e) => println!("Sound is disabled: {}", e),
+    }
+}
+
+/// Sets up the assets, the sound, the controls, and the recording or the
+/// playback of the inputs.
+/// `size` is the size the window was opened with.
+fn prepare(context: &mut Phi, config: &Config, replay: Option<Replay>, size: (u32, u32)) {
+    context.assets.hot_reload = config.hot_reload;
+    context.audio.set_volume(Volume::Master, config.volume);
+    context.audio.set_volume(Volume::Sfx, config.sfx_volume);
+    context.audio.set_volume(Volume::Music, config.music_volume);
+
+    if let Some(seed) = config.seed {
+        context.rng = Rng::new(seed);
+    }
+
+    match replay {
+        Some(replay) => context.play(replay),
+        None => match ::phi::input::Bindings::load(::phi::input::CONTROLS_PATH) {
+            Ok(bindings) => context.events.bindings = bindings,
+            Err(e) => println!("Using the default controls. {}", e),
+        },
+    }
+
+    if config.record.is_some() {
+        let replay = Replay::new(context.rng.seed(), size, context.events.bindings.clone());
+        context.events.record(replay);
+    }
+}
+
+/// Saves the recording, if there is one.
+fn finish(context: &mut Phi, config: &Config) {
+    if let (Some(path), Some(replay)) = (config.record.as_ref(), context.events.take_recording()) {
+        match replay.save(path) {
+            Ok(()) => println!("Saved the replay to {}", path),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+/// Runs the views for a fixed number of ticks, rendering into the offscreen
+/// buffer after every one of them, and returns the draw calls of the last
+/// frame.
+pub fn run_headless<F>(context: &mut Phi, ticks: u32, tick_rate: f64, init: F) -> Result<Vec<DrawCall>, Error>
+where F: Fn(&mut Phi) -> Result<Box<View>, Error> {
+    let root = try!(init(context));
+    let mut views = ViewStack::new(context, root);
+
+    for _ in 0..ticks {
+        context.events.pump(&*context.renderer);
+        if !views.update(context, 1.0 / tick_rate) {
+            break;
+        }
+        views.render(context, 0.0);
+    }
+
+    Ok(context.renderer.last_frame().map(|calls| calls.to_vec()).unwrap_or(vec![]))
 }
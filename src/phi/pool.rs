@@ -0,0 +1,253 @@
+/// A handle to a value in a `Pool`. Once the value is removed, the handle
+/// no longer reaches anything, even after its slot is used again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: usize,
+    generation: u32,
+}
+
+impl Handle {
+    /// The slot of the value, which is only ever used by one value at once.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+struct Slot {
+    /// The generation of the value in the slot, or of the next one if it
+    /// is free.
+    generation: u32,
+    /// Where the value is in `values`, while the slot is used.
+    value: Option<usize>,
+}
+
+/// Values which come and go all the time, such as bullets. Their slots are
+/// used again once they are removed, and the values themselves are packed
+/// next to each other, so that going through all of them is quick and
+/// keeping them around allocates nothing once the pool has grown.
+pub struct Pool<T> {
+    slots: Vec<Slot>,
+    /// The slots which are free, the last one freed at the end.
+    free: Vec<usize>,
+    values: Vec<T>,
+    /// The slot of every value in `values`.
+    owners: Vec<usize>,
+}
+
+impl<T> Pool<T> {
+    pub fn new() -> Pool<T> {
+        Pool {
+            slots: vec![],
+            free: vec![],
+            values: vec![],
+            owners: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, value: None });
+                self.slots.len() - 1
+            },
+        };
+
+        self.slots[index].value = Some(self.values.len());
+        self.values.push(value);
+        self.owners.push(index);
+        Handle { index: index, generation: self.slots[index].generation }
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let position = match self.position(handle) {
+            Some(position) => position,
+            None => return None,
+        };
+
+        // the last value takes the place of the removed one
+        let value = self.values.swap_remove(position);
+        self.owners.swap_remove(position);
+        if position < self.owners.len() {
+            self.slots[self.owners[position]].value = Some(position);
+        }
+
+        let slot = &mut self.slots[handle.index];
+        slot.value = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        Some(value)
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.position(handle).is_some()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.position(handle).map(move |position| &self.values[position])
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        match self.position(handle) {
+            Some(position) => Some(&mut self.values[position]),
+            None => None,
+        }
+    }
+
+    /// Removes every value, keeping the memory for the next ones.
+    pub fn clear(&mut self) {
+        for &index in &self.owners {
+            let slot = &mut self.slots[index];
+            slot.value = None;
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free.push(index);
+        }
+        self.values.clear();
+        self.owners.clear();
+    }
+
+    /// Every value, in the order in which they are packed.
+    pub fn iter(&self) -> Iter<T> {
+        Iter {
+            slots: &self.slots,
+            values: self.owners.iter().zip(self.values.iter()),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<T> {
+        IterMut {
+            slots: &self.slots,
+            values: self.owners.iter().zip(self.values.iter_mut()),
+        }
+    }
+
+    /// Where the value of `handle` is in `values`, if it is still there.
+    fn position(&self, handle: Handle) -> Option<usize> {
+        match self.slots.get(handle.index) {
+            Some(slot) if slot.generation == handle.generation => slot.value,
+            _ => None,
+        }
+    }
+}
+
+pub struct Iter<'a, T: 'a> {
+    slots: &'a [Slot],
+    values: ::std::iter::Zip<::std::slice::Iter<'a, usize>, ::std::slice::Iter<'a, T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (Handle, &'a T);
+
+    fn next(&mut self) -> Option<(Handle, &'a T)> {
+        let slots = self.slots;
+        self.values.next().map(|(&index, value)| {
+            (Handle { index: index, generation: slots[index].generation }, value)
+        })
+    }
+}
+
+pub struct IterMut<'a, T: 'a> {
+    slots: &'a [Slot],
+    values: ::std::iter::Zip<::std::slice::Iter<'a, usize>, ::std::slice::IterMut<'a, T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = (Handle, &'a mut T);
+
+    fn next(&mut self) -> Option<(Handle, &'a mut T)> {
+        let slots = self.slots;
+        self.values.next().map(|(&index, value)| {
+            (Handle { index: index, generation: slots[index].generation }, value)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pool;
+
+    #[test]
+    fn stale_handles_reach_nothing() {
+        let mut pool = Pool::new();
+        let a = pool.insert("a");
+        assert_eq!(pool.remove(a), Some("a"));
+
+        // the slot is reused, under another generation
+        let b = pool.insert("b");
+        assert_eq!(b.index(), a.index());
+        assert_ne!(b, a);
+        assert!(!pool.contains(a));
+        assert_eq!(pool.get(a), None);
+        assert_eq!(pool.get_mut(a), None);
+        assert_eq!(pool.remove(a), None);
+        assert_eq!(pool.get(b), Some(&"b"));
+    }
+
+    #[test]
+    fn removing_keeps_the_others_reachable() {
+        let mut pool = Pool::new();
+        let a = pool.insert(1);
+        let b = pool.insert(2);
+        let c = pool.insert(3);
+
+        // `c` takes the place of `a`
+        pool.remove(a);
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.get(b), Some(&2));
+        assert_eq!(pool.get(c), Some(&3));
+        *pool.get_mut(c).unwrap() += 10;
+        assert_eq!(pool.get(c), Some(&13));
+
+        // removing the last value leaves the others in place
+        pool.remove(c);
+        assert_eq!(pool.get(b), Some(&2));
+        pool.remove(b);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn clearing_makes_every_handle_stale() {
+        let mut pool = Pool::new();
+        let handles: Vec<_> = (0..3).map(|i| pool.insert(i)).collect();
+        pool.clear();
+
+        assert_eq!(pool.len(), 0);
+        assert!(handles.iter().all(|&handle| !pool.contains(handle)));
+
+        // the slots are reused, but not the handles
+        let again: Vec<_> = (0..3).map(|i| pool.insert(i)).collect();
+        for handle in &handles {
+            assert!(again.iter().any(|other| other.index() == handle.index()));
+            assert!(!again.contains(handle));
+            assert!(!pool.contains(*handle));
+        }
+    }
+
+    #[test]
+    fn iterates_over_what_is_left() {
+        let mut pool = Pool::new();
+        let handles: Vec<_> = (0..5).map(|i| pool.insert(i * 10)).collect();
+        pool.remove(handles[1]);
+        pool.remove(handles[3]);
+
+        let mut left: Vec<_> = pool.iter().map(|(handle, &value)| (handle, value)).collect();
+        left.sort_by_key(|&(_, value)| value);
+        assert_eq!(left, vec![(handles[0], 0), (handles[2], 20), (handles[4], 40)]);
+
+        for (_, value) in pool.iter_mut() {
+            *value += 1;
+        }
+        for (handle, &value) in pool.iter() {
+            assert_eq!(pool.get(handle), Some(&value));
+        }
+        assert_eq!(pool.get(handles[4]), Some(&41));
+
+        // a handle from the iteration is as good as the one from `insert`
+        let reused = pool.insert(50);
+        assert!(pool.iter().any(|(handle, &value)| handle == reused && value == 50));
+    }
+}
@@ -0,0 +1,316 @@
+use ::phi::input::{Bindings, InputEvent};
+use ::sdl2::controller::{Axis, Button};
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::mouse::Mouse;
+use ::std::fs::File;
+use ::std::io::{Read, Write};
+
+/// First line of every replay file, followed by the format version.
+const HEADER: &'static str = "arcade-rs replay";
+const VERSION: u32 = 1;
+
+/// Everything needed to play a run again exactly as it happened: the seed
+/// of the random number generator, the size of the window, the controls as
+/// they were when the recording started, and the inputs which were applied
+/// during every tick.
+#[derive(Clone)]
+pub struct Replay {
+    pub seed: u32,
+    pub size: (u32, u32),
+    pub bindings: Bindings,
+    ticks: Vec<Vec<InputEvent>>,
+    /// The next tick to be played back.
+    cursor: usize,
+}
+
+impl Replay {
+    pub fn new(seed: u32, size: (u32, u32), bindings: Bindings) -> Replay {
+        Replay {
+            seed: seed,
+            size: size,
+            bindings: bindings,
+            ticks: vec![],
+            cursor: 0,
+        }
+    }
+
+    /// Records the inputs of a new tick.
+    pub fn push_tick(&mut self, inputs: Vec<InputEvent>) {
+        self.ticks.push(inputs);
+    }
+
+    /// The inputs of the next tick to be played back.
+    pub fn next_tick(&mut self) -> Vec<InputEvent> {
+        let inputs = self.ticks.get(self.cursor).cloned().unwrap_or(vec![]);
+        self.cursor += 1;
+        inputs
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.ticks.len()
+    }
+
+    /// Writes the replay in the format read by `parse`.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        File::create(path)
+            .and_then(|mut file| file.write_all(self.to_text().as_bytes()))
+            .map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    /// The replay as text, with one line per recorded input, which makes
+    /// it easy to attach to a bug report.
+    pub fn to_text(&self) -> String {
+        let mut contents = format!("{} {}\n", HEADER, VERSION);
+        contents.push_str(&format!("seed {}\n", self.seed));
+        contents.push_str(&format!("size {} {}\n", self.size.0, self.size.1));
+        for line in self.bindings.to_config().lines() {
+            contents.push_str(&format!("bind {}\n", line));
+        }
+        contents.push_str(&format!("ticks {}\n", self.ticks.len()));
+
+        for (tick, inputs) in self.ticks.iter().enumerate() {
+            for input in inputs {
+                contents.push_str(&format!("{} {}\n", tick, encode(input)));
+            }
+        }
+        contents
+    }
+
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let mut contents = String::new();
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|e| format!("Could not read {}: {}", path, e)));
+
+        Replay::parse(&contents, path)
+    }
+
+    /// Parses a replay written by `to_text`. `origin` is only used to make
+    /// error messages point to the right place.
+    pub fn parse(contents: &str, origin: &str) -> Result<Replay, String> {
+        let invalid = |line: usize| format!("{}:{}: invalid replay", origin, line + 1);
+        let mut lines = contents.lines().enumerate();
+
+        match lines.next() {
+            Some((_, header)) if header == format!("{} {}", HEADER, VERSION) => {},
+            _ => return Err(format!("{} is not a version {} replay", origin, VERSION)),
+        }
+
+        let mut replay = Replay::new(0, (0, 0), Bindings::default());
+        let mut bindings = String::new();
+
+        for (i, line) in lines {
+            let mut parts = line.splitn(2, ' ');
+            let first = parts.next().unwrap_or("");
+            let rest = parts.next().unwrap_or("");
+
+            match first {
+                "seed" => {
+                    replay.seed = try!(rest.parse().map_err(|_| invalid(i)));
+                },
+                "size" => {
+                    let size: Vec<u32> = rest.split(' ').filter_map(|n| n.parse().ok()).collect();
+                    if size.len() != 2 {
+                        return Err(invalid(i));
+                    }
+                    replay.size = (size[0], size[1]);
+                },
+                "bind" => {
+                    bindings.push_str(rest);
+                    bindings.push('\n');
+                },
+                "ticks" => {
+                    let ticks: usize = try!(rest.parse().map_err(|_| invalid(i)));
+                    replay.ticks = vec![vec![]; ticks];
+                },
+                tick => {
+                    let tick: usize = try!(tick.parse().map_err(|_| invalid(i)));
+                    let input = try!(decode(rest).ok_or(invalid(i)));
+                    match replay.ticks.get_mut(tick) {
+                        Some(inputs) => inputs.push(input),
+                        None => return Err(invalid(i)),
+                    }
+                },
+            }
+        }
+
+        replay.bindings = try!(Bindings::parse(&bindings, origin));
+        Ok(replay)
+    }
+}
+
+
+fn encode(input: &InputEvent) -> String {
+    match *input {
+        InputEvent::KeyDown(code) => format!("key_down {}", code.name()),
+        InputEvent::KeyUp(code) => format!("key_up {}", code.name()),
+        InputEvent::ButtonDown(button) => format!("button_down {}", button.string()),
+        InputEvent::ButtonUp(button) => format!("button_up {}", button.string()),
+        InputEvent::Axis(axis, value) => format!("axis {} {}", axis_name(axis), value),
+        InputEvent::ControllersGone => "controllers_gone".to_string(),
+        InputEvent::MouseMove(x, y) => format!("mouse_move {} {}", x, y),
+        InputEvent::MouseDown(button, x, y) => format!("mouse_down {} {} {}", mouse_name(button), x, y),
+        InputEvent::MouseUp(button, x, y) => format!("mouse_up {} {} {}", mouse_name(button), x, y),
+        InputEvent::Wheel(x, y) => format!("wheel {} {}", x, y),
+        InputEvent::Resize(w, h) => format!("resize {} {}", w, h),
+        InputEvent::Other(ref name) => format!("other {}", name),
+    }
+}
+
+fn decode(line: &str) -> Option<InputEvent> {
+    let mut parts = line.splitn(2, ' ');
+    let kind = parts.next().unwrap_or("");
+    let rest = parts.next().unwrap_or("");
+    let args: Vec<&str> = rest.split(' ').collect();
+
+    let f64_at = |i: usize| args.get(i).and_then(|arg| arg.parse::<f64>().ok());
+    let i32_at = |i: usize| args.get(i).and_then(|arg| arg.parse::<i32>().ok());
+
+    match kind {
+        // key names may contain spaces, e.g. "Left Shift"
+        "key_down" => Keycode::from_name(rest).map(InputEvent::KeyDown),
+        "key_up" => Keycode::from_name(rest).map(InputEvent::KeyUp),
+        "button_down" => Button::from_string(rest).map(InputEvent::ButtonDown),
+        "button_up" => Button::from_string(rest).map(InputEvent::ButtonUp),
+        "axis" => match (axis_from_name(args[0]), f64_at(1)) {
+            (Some(axis), Some(value)) => Some(InputEvent::Axis(axis, value)),
+            _ => None,
+        },
+        "controllers_gone" => Some(InputEvent::ControllersGone),
+        "mouse_move" => match (f64_at(0), f64_at(1)) {
+            (Some(x), Some(y)) => Some(InputEvent::MouseMove(x, y)),
+            _ => None,
+        },
+        "mouse_down" => match (mouse_from_name(args[0]), f64_at(1), f64_at(2)) {
+            (Some(button), Some(x), Some(y)) => Some(InputEvent::MouseDown(button, x, y)),
+            _ => None,
+        },
+        "mouse_up" => match (mouse_from_name(args[0]), f64_at(1), f64_at(2)) {
+            (Some(button), Some(x), Some(y)) => Some(InputEvent::MouseUp(button, x, y)),
+            _ => None,
+        },
+        "wheel" => match (i32_at(0), i32_at(1)) {
+            (Some(x), Some(y)) => Some(InputEvent::Wheel(x, y)),
+            _ => None,
+        },
+        "resize" => match (i32_at(0), i32_at(1)) {
+            (Some(w), Some(h)) => Some(InputEvent::Resize(w as u32, h as u32)),
+            _ => None,
+        },
+        "other" => Some(InputEvent::Other(rest.to_string())),
+        _ => None,
+    }
+}
+
+fn axis_name(axis: Axis) -> &'static str {
+    match axis {
+        Axis::LeftX => "leftx",
+        Axis::LeftY => "lefty",
+        Axis::RightX => "rightx",
+        Axis::RightY => "righty",
+        Axis::TriggerLeft => "lefttrigger",
+        Axis::TriggerRight => "righttrigger",
+    }
+}
+
+fn axis_from_name(name: &str) -> Option<Axis> {
+    match name {
+        "leftx" => Some(Axis::LeftX),
+        "lefty" => Some(Axis::LeftY),
+        "rightx" => Some(Axis::RightX),
+        "righty" => Some(Axis::RightY),
+        "lefttrigger" => Some(Axis::TriggerLeft),
+        "righttrigger" => Some(Axis::TriggerRight),
+        _ => None,
+    }
+}
+
+fn mouse_name(button: Mouse) -> String {
+    match button {
+        Mouse::Left => "left".to_string(),
+        Mouse::Middle => "middle".to_string(),
+        Mouse::Right => "right".to_string(),
+        Mouse::X1 => "x1".to_string(),
+        Mouse::X2 => "x2".to_string(),
+        Mouse::Unknown(n) => n.to_string(),
+    }
+}
+
+fn mouse_from_name(name: &str) -> Option<Mouse> {
+    match name {
+        "left" => Some(Mouse::Left),
+        "middle" => Some(Mouse::Middle),
+        "right" => Some(Mouse::Right),
+        "x1" => Some(Mouse::X1),
+        "x2" => Some(Mouse::X2),
+        n => n.parse().ok().map(Mouse::Unknown),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ::phi::input::{Action, Bindings, InputEvent};
+    use ::sdl2::controller::{Axis, Button};
+    use ::sdl2::keyboard::Keycode;
+    use ::sdl2::mouse::Mouse;
+    use super::{decode, encode, Replay};
+
+    fn inputs() -> Vec<InputEvent> {
+        vec![
+            InputEvent::KeyDown(Keycode::Space),
+            InputEvent::KeyUp(Keycode::Num1),
+            InputEvent::ButtonDown(Button::DPadUp),
+            InputEvent::ButtonUp(Button::A),
+            InputEvent::Axis(Axis::LeftY, -0.75),
+            InputEvent::ControllersGone,
+            InputEvent::MouseMove(12.5, 300.0),
+            InputEvent::MouseDown(Mouse::Left, 1.0, 2.0),
+            InputEvent::MouseUp(Mouse::Unknown(9), 3.0, 4.0),
+            InputEvent::Wheel(-1, 2),
+            InputEvent::Resize(1024, 768),
+            InputEvent::Other("focus_lost".to_string()),
+        ]
+    }
+
+    #[test]
+    fn inputs_survive_encoding() {
+        for input in inputs() {
+            assert_eq!(decode(&encode(&input)), Some(input));
+        }
+        assert_eq!(decode("axis nowhere 1"), None);
+        assert_eq!(decode("mouse_move 1"), None);
+    }
+
+    #[test]
+    fn replays_survive_encoding() {
+        let mut bindings = Bindings::default();
+        bindings.set(Action::Fire, vec![Keycode::X, Keycode::Return]);
+        bindings.deadzone = 0.4;
+
+        let mut replay = Replay::new(42, (640, 480), bindings);
+        replay.push_tick(inputs());
+        replay.push_tick(vec![]);
+        replay.push_tick(vec![InputEvent::KeyDown(Keycode::Escape)]);
+        // ticks without any input at the end still count
+        replay.push_tick(vec![]);
+
+        let mut parsed = Replay::parse(&replay.to_text(), "test.replay").unwrap();
+        assert_eq!(parsed.seed, 42);
+        assert_eq!(parsed.size, (640, 480));
+        assert_eq!(parsed.bindings.to_config(), replay.bindings.to_config());
+        for _ in 0..4 {
+            assert!(!parsed.is_finished());
+            assert_eq!(parsed.next_tick(), replay.next_tick());
+        }
+        assert!(parsed.is_finished());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Replay::parse("seed 1\n", "test.replay").is_err());
+        let text = Replay::new(1, (1, 1), Bindings::default()).to_text() + "5 key_down Space\n";
+        assert_eq!(Replay::parse(&text, "test.replay").err(),
+                   Some(format!("test.replay:{}: invalid replay", text.lines().count())));
+    }
+}
@@ -0,0 +1,85 @@
+use ::rand::{Rng as RandRng, SeedableRng, XorShiftRng};
+
+/// A seeded random number generator. Everything random in the game should
+/// be drawn from the one owned by `Phi`, so that a run can be reproduced
+/// from its seed.
+pub struct Rng {
+    seed: u32,
+    inner: XorShiftRng,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Rng {
+        Rng {
+            seed: seed,
+            // a xorshift generator must not be seeded with only zeroes
+            inner: XorShiftRng::from_seed([0x193a_6754, 0xa8a7_d469, 0x9783_0e05, seed]),
+        }
+    }
+
+    /// Creates a generator with an arbitrary seed.
+    pub fn from_entropy() -> Rng {
+        Rng::new(::rand::random())
+    }
+
+    /// The seed the generator was created with.
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// A number between `low` (inclusive) and `high` (exclusive).
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + self.inner.gen::<f64>() * (high - low)
+    }
+
+    /// Returns `true` with a probability of `p`, between 0 and 1.
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.inner.gen::<f64>() < p
+    }
+
+    /// One of the `items`, or `None` if there are none.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            let i = self.inner.gen_range(0, items.len());
+            Some(&items[i])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    fn draws(seed: u32) -> Vec<f64> {
+        let mut rng = Rng::new(seed);
+        (0..20).map(|_| rng.range(0.0, 1.0)).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_numbers() {
+        assert_eq!(draws(42), draws(42));
+        assert_eq!(draws(0), draws(0));
+        assert_eq!(Rng::new(42).seed(), 42);
+    }
+
+    #[test]
+    fn different_seeds_give_different_numbers() {
+        assert!(draws(42) != draws(43));
+        assert!(draws(0) != draws(1));
+    }
+
+    #[test]
+    fn draws_within_bounds() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let x = rng.range(-2.0, 3.0);
+            assert!(x >= -2.0 && x < 3.0);
+        }
+        assert!(rng.chance(1.0));
+        assert!(!rng.chance(0.0));
+        assert_eq!(rng.pick::<u32>(&[]), None);
+        assert_eq!(rng.pick(&[5]), Some(&5));
+    }
+}
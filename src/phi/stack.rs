@@ -0,0 +1,227 @@
+use ::phi::{Phi, View, ViewAction};
+
+/// The views which are currently alive, the last one being the one which
+/// is rendered and receives the events. Views below it are paused and
+/// resume exactly where they left off once everything above them is popped.
+pub struct ViewStack {
+    views: Vec<Box<View>>,
+}
+
+impl ViewStack {
+    pub fn new(phi: &mut Phi, root: Box<View>) -> ViewStack {
+        let mut stack = ViewStack { views: vec![] };
+        stack.push(phi, root);
+        stack
+    }
+
+    /// Updates the view on top of the stack and applies the action it
+    /// returned. Returns `false` once the game should quit.
+    pub fn update(&mut self, phi: &mut Phi, dt: f64) -> bool {
+        let action = match self.views.last_mut() {
+            Some(view) => view.update(phi, dt),
+            None => return false,
+        };
+
+        match action {
+            ViewAction::None => {},
+            ViewAction::Quit => self.clear(phi),
+            ViewAction::Push(view) => self.push(phi, view),
+            ViewAction::Pop => {
+                self.pop(phi);
+                if let Some(view) = self.views.last_mut() {
+                    view.on_resume(phi);
+                }
+            },
+            ViewAction::Replace(view) => {
+                self.pop(phi);
+                self.views.push(view);
+                self.views.last_mut().unwrap().on_enter(phi);
+            },
+            ViewAction::Reset(view) => {
+                self.clear(phi);
+                self.push(phi, view);
+            },
+        }
+
+        !self.views.is_empty()
+    }
+
+    /// Draws the view on top of the stack, over the views it lets through
+    /// if it is an overlay, and presents the frame. The views below the top
+    /// one are not updated, so they are drawn as of their last tick.
+    pub fn render(&mut self, phi: &mut Phi, alpha: f64) {
+        if self.views.is_empty() {
+            return;
+        }
+
+        let top = self.views.len() - 1;
+        let first = self.views.iter().rposition(|view| !view.is_overlay()).unwrap_or(0);
+        for (i, view) in self.views.iter_mut().enumerate().skip(first) {
+            view.render(phi, if i == top { alpha } else { 1.0 });
+        }
+        phi.renderer.present();
+    }
+
+    fn push(&mut self, phi: &mut Phi, mut view: Box<View>) {
+        if let Some(top) = self.views.last_mut() {
+            top.on_pause(phi);
+        }
+        view.on_enter(phi);
+        self.views.push(view);
+    }
+
+    fn pop(&mut self, phi: &mut Phi) {
+        if let Some(mut view) = self.views.pop() {
+            view.on_exit(phi);
+        }
+        // the textures which only this view used can go
+        phi.assets.collect();
+    }
+
+    fn clear(&mut self, phi: &mut Phi) {
+        while !self.views.is_empty() {
+            self.pop(phi);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::phi::{Phi, View, ViewAction};
+    use ::phi::testing::headless;
+    use ::std::cell::RefCell;
+    use ::std::rc::Rc;
+    use super::ViewStack;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// Writes down what happens to it in `log`, and returns `actions` from
+    /// its updates, one by one.
+    struct Recorder {
+        name: &'static str,
+        log: Log,
+        actions: Vec<ViewAction>,
+    }
+
+    impl Recorder {
+        fn new(name: &'static str, log: &Log, actions: Vec<ViewAction>) -> Box<View> {
+            Box::new(Recorder { name: name, log: log.clone(), actions: actions })
+        }
+
+        fn record(&self, event: &str) {
+            self.log.borrow_mut().push(format!("{} {}", self.name, event));
+        }
+    }
+
+    impl View for Recorder {
+        fn update(&mut self, _phi: &mut Phi, _dt: f64) -> ViewAction {
+            self.record("update");
+            if self.actions.is_empty() {
+                ViewAction::None
+            } else {
+                self.actions.remove(0)
+            }
+        }
+
+        fn render(&mut self, _phi: &mut Phi, _alpha: f64) {}
+
+        fn on_enter(&mut self, _phi: &mut Phi) { self.record("enter"); }
+        fn on_pause(&mut self, _phi: &mut Phi) { self.record("pause"); }
+        fn on_resume(&mut self, _phi: &mut Phi) { self.record("resume"); }
+        fn on_exit(&mut self, _phi: &mut Phi) { self.record("exit"); }
+    }
+
+    /// Runs `ticks` updates of a stack starting with `root`, and returns
+    /// whether it is still running along with what happened.
+    fn run(ticks: usize, root: &Fn(&Log) -> Box<View>) -> (bool, Vec<String>) {
+        headless(1, |phi| {
+            let log: Log = Rc::new(RefCell::new(vec![]));
+            let mut stack = ViewStack::new(phi, root(&log));
+            let mut running = true;
+            for _ in 0..ticks {
+                running = stack.update(phi, 1.0 / 60.0);
+            }
+            let events = log.borrow().clone();
+            (running, events)
+        })
+    }
+
+    #[test]
+    fn pushing_pauses_the_top_view() {
+        let (running, events) = run(2, &|log| {
+            Recorder::new("game", log, vec![ViewAction::Push(Recorder::new("menu", log, vec![]))])
+        });
+
+        assert!(running);
+        assert_eq!(events, vec!["game enter", "game update", "game pause", "menu enter", "menu update"]);
+    }
+
+    #[test]
+    fn popping_resumes_the_view_below() {
+        let (running, events) = run(3, &|log| {
+            let menu = Recorder::new("menu", log, vec![ViewAction::Pop]);
+            Recorder::new("game", log, vec![ViewAction::Push(menu)])
+        });
+
+        assert!(running);
+        assert_eq!(events, vec![
+            "game enter", "game update", "game pause", "menu enter",
+            "menu update", "menu exit", "game resume",
+            "game update",
+        ]);
+    }
+
+    #[test]
+    fn replacing_does_not_resume_the_view_below() {
+        let (running, events) = run(3, &|log| {
+            let over = Recorder::new("over", log, vec![]);
+            let menu = Recorder::new("menu", log, vec![ViewAction::Replace(over)]);
+            Recorder::new("game", log, vec![ViewAction::Push(menu)])
+        });
+
+        assert!(running);
+        assert_eq!(events, vec![
+            "game enter", "game update", "game pause", "menu enter",
+            "menu update", "menu exit", "over enter",
+            "over update",
+        ]);
+    }
+
+    #[test]
+    fn resetting_exits_every_view() {
+        let (running, events) = run(3, &|log| {
+            let title = Recorder::new("title", log, vec![]);
+            let menu = Recorder::new("menu", log, vec![ViewAction::Reset(title)]);
+            Recorder::new("game", log, vec![ViewAction::Push(menu)])
+        });
+
+        assert!(running);
+        assert_eq!(events, vec![
+            "game enter", "game update", "game pause", "menu enter",
+            "menu update", "menu exit", "game exit", "title enter",
+            "title update",
+        ]);
+    }
+
+    #[test]
+    fn popping_the_last_view_quits() {
+        let (running, events) = run(1, &|log| Recorder::new("game", log, vec![ViewAction::Pop]));
+
+        assert!(!running);
+        assert_eq!(events, vec!["game enter", "game update", "game exit"]);
+    }
+
+    #[test]
+    fn quitting_exits_every_view() {
+        let (running, events) = run(2, &|log| {
+            let menu = Recorder::new("menu", log, vec![ViewAction::Quit]);
+            Recorder::new("game", log, vec![ViewAction::Push(menu)])
+        });
+
+        assert!(!running);
+        assert_eq!(events, vec![
+            "game enter", "game update", "game pause", "menu enter",
+            "menu update", "menu exit", "game exit",
+        ]);
+    }
+}
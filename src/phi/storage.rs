@@ -0,0 +1,44 @@
+use ::std::env;
+use ::std::fs::{self, File};
+use ::std::io::Write;
+use ::std::path::{Path, PathBuf};
+
+/// The directory, inside the platform's data directory, which holds the
+/// files of the game.
+const APP_DIR: &'static str = "arcade-rs";
+
+/// Where the files which belong to the player, such as their high scores,
+/// are kept:
+///
+/// * `$XDG_DATA_HOME/arcade-rs`, or `~/.local/share/arcade-rs`, on Linux;
+/// * `~/Library/Application Support/arcade-rs` on OS X;
+/// * `%APPDATA%\arcade-rs` on Windows.
+///
+/// Falls back to the working directory if none of those can be found.
+pub fn data_dir() -> PathBuf {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".local").join("share")))
+    };
+
+    base.map(|base| base.join(APP_DIR)).unwrap_or(PathBuf::from("."))
+}
+
+/// Writes `contents` to `path`, creating its directory if needed. The file
+/// is written next to `path` first and then moved over it, so that the old
+/// version survives if the game dies midway.
+pub fn write(path: &Path, contents: &str) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+
+    let dir = path.parent().map_or(Ok(()), fs::create_dir_all);
+    dir.and_then(|_| File::create(&tmp))
+        .and_then(|mut file| file.write_all(contents.as_bytes()).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
@@ -0,0 +1,32 @@
+use ::phi::Phi;
+use ::phi::rng::Rng;
+use ::std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+
+/// SDL can only be initialized once at a time, so the tests which need it
+/// take turns.
+static SDL_BUSY: AtomicBool = ATOMIC_BOOL_INIT;
+
+struct Turn;
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        SDL_BUSY.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Calls `f` with an offscreen context of 800x600 whose random numbers are
+/// drawn from `seed`, and which uses the default controls.
+pub fn headless<F, R>(seed: u32, f: F) -> R
+where F: FnOnce(&mut Phi) -> R {
+    while SDL_BUSY.compare_and_swap(false, true, Ordering::SeqCst) {
+        ::std::thread::yield_now();
+    }
+    let _turn = Turn;
+
+    ::std::env::set_var("SDL_AUDIODRIVER", "dummy");
+    let sdl_context = ::sdl2::init().unwrap();
+    let _ttf_context = ::sdl2_ttf::init().unwrap();
+    let mut phi = Phi::headless(&sdl_context, 800, 600).unwrap();
+    phi.rng = Rng::new(seed);
+    f(&mut phi)
+}
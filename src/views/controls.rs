@@ -0,0 +1,196 @@
+use ::phi::{Error, Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::input::{Action, CONTROLS_PATH};
+use ::views::error::ErrorView;
+use ::views::shared::BackgroundSet;
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::mouse::Mouse;
+use ::sdl2::pixels::Color;
+
+// Consts
+const FONT: &'static str = "assets/belligerent.ttf";
+const FONT_SIZE: i32 = 24;
+const ROW_H: f64 = 36.0;
+const BOX_W: f64 = 520.0;
+
+// Types
+
+struct Row {
+    action: Action,
+    label: Sprite,
+    keys: Sprite,
+}
+
+/// Lists every `Action` with the keys bound to it, and lets the player
+/// rebind them. The bindings are saved when leaving the view.
+pub struct ControlsView {
+    rows: Vec<Row>,
+    selected: usize,
+    /// Whether the next key pressed gets bound to the selected action.
+    waiting: bool,
+    bgs: BackgroundSet,
+}
+
+impl ControlsView {
+    pub fn new(phi: &mut Phi, bgs: BackgroundSet) -> Result<ControlsView, Error> {
+        let mut rows = vec![];
+        for action in Action::all() {
+            rows.push(Row {
+                action: action,
+                label: try!(ControlsView::text(phi, &action.label())),
+                keys: try!(ControlsView::keys_sprite(phi, action, false)),
+            });
+        }
+
+        Ok(ControlsView {
+            rows: rows,
+            selected: 0,
+            waiting: false,
+            bgs: bgs,
+        })
+    }
+
+    fn text(phi: &mut Phi, text: &str) -> Result<Sprite, Error> {
+        phi.ttf_str_sprite(text, FONT, FONT_SIZE, Color::RGB(220, 220, 220))
+    }
+
+    fn keys_sprite(phi: &mut Phi, action: Action, waiting: bool) -> Result<Sprite, Error> {
+        let label = if waiting {
+            "Press a key...".to_string()
+        } else {
+            phi.events.bindings.keys_label(action)
+        };
+
+        // an empty string cannot be rendered
+        ControlsView::text(phi, if label.is_empty() { "-" } else { &label })
+    }
+
+    /// The region of the window in which the `i`th row is shown.
+    fn row_rect(&self, window: (f64, f64), i: usize) -> Rectangle {
+        let box_h = self.rows.len() as f64 * ROW_H;
+        Rectangle {
+            x: (window.0 - BOX_W) / 2.0,
+            y: (window.1 - box_h) / 2.0 + ROW_H * i as f64,
+            w: BOX_W,
+            h: ROW_H,
+        }
+    }
+
+    fn refresh_selected(&mut self, phi: &mut Phi) -> Result<(), Error> {
+        let row = &mut self.rows[self.selected];
+        row.keys = try!(ControlsView::keys_sprite(phi, row.action, self.waiting));
+        Ok(())
+    }
+}
+
+impl View for ControlsView {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit;
+        }
+
+        self.bgs.update(dt);
+
+        if self.waiting {
+            if let Some(&key) = phi.events.now.pressed.first() {
+                // escape cancels the rebinding
+                if key != Keycode::Escape {
+                    let action = self.rows[self.selected].action;
+                    phi.events.bindings.set(action, vec![key]);
+                }
+                self.waiting = false;
+                if let Err(e) = self.refresh_selected(phi) {
+                    return ErrorView::show(phi, e);
+                }
+            }
+            return ViewAction::None;
+        }
+
+        if phi.events.pressed(Action::Pause) {
+            if let Err(e) = phi.events.bindings.save(CONTROLS_PATH) {
+                println!("{}", e);
+            }
+            return ViewAction::Pop;
+        }
+
+        // hovering a row selects it, and clicking it starts rebinding
+        let window = phi.output_size();
+        let hovered = (0..self.rows.len())
+            .find(|&i| self.row_rect(window, i).contains_point(phi.events.mouse));
+        if let Some(i) = hovered {
+            if phi.events.now.mouse_moved || phi.events.mouse_pressed(Mouse::Left) {
+                self.selected = i;
+            }
+        }
+
+        if phi.events.pressed(Action::Confirm) ||
+            (hovered.is_some() && phi.events.mouse_pressed(Mouse::Left)) {
+            self.waiting = true;
+            if let Err(e) = self.refresh_selected(phi) {
+                return ErrorView::show(phi, e);
+            }
+            return ViewAction::None;
+        }
+
+        if phi.events.pressed(Action::MoveUp) {
+            self.selected = if self.selected == 0 {
+                self.rows.len() - 1
+            } else {
+                self.selected - 1
+            };
+        }
+
+        if phi.events.pressed(Action::MoveDown) {
+            self.selected = (self.selected + 1) % self.rows.len();
+        }
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _alpha: f64) {
+        // clear the screen
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        // bgs
+        self.bgs.render(&mut *phi.renderer);
+
+        let (win_w, win_h) = phi.output_size();
+        let margin = 10.0;
+        let box_h = self.rows.len() as f64 * ROW_H;
+        let box_x = (win_w - BOX_W) / 2.0;
+        let box_y = (win_h - box_h) / 2.0;
+
+        phi.renderer.set_draw_color(Color::RGB(140, 30, 140));
+        phi.renderer.fill_rect(Rectangle {
+            w: BOX_W + margin * 2.0,
+            h: box_h + margin * 2.0,
+            x: box_x - margin,
+            y: box_y - margin,
+        });
+
+        phi.renderer.set_draw_color(Color::RGB(70, 15, 70));
+        phi.renderer.fill_rect(self.row_rect((win_w, win_h), self.selected));
+
+        for (i, row) in self.rows.iter().enumerate() {
+            let y = box_y + ROW_H * i as f64;
+
+            let (w, h) = row.label.size();
+            phi.renderer.copy_sprite(&row.label, Rectangle {
+                x: box_x + margin,
+                y: y + (ROW_H - h) / 2.0,
+                w: w,
+                h: h,
+            });
+
+            let (w, h) = row.keys.size();
+            phi.renderer.copy_sprite(&row.keys, Rectangle {
+                x: box_x + BOX_W - margin - w,
+                y: y + (ROW_H - h) / 2.0,
+                w: w,
+                h: h,
+            });
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use ::phi::{Error, Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::input::Action;
+use ::sdl2::pixels::Color;
+
+// Consts
+const FONT: &'static str = "assets/belligerent.ttf";
+const FONT_SIZE: i32 = 24;
+const LINE_H: f64 = 36.0;
+
+/// Tells the player that something went wrong instead of crashing, and
+/// lets them go back to the main menu.
+pub struct ErrorView {
+    lines: Vec<Sprite>,
+}
+
+impl ErrorView {
+    pub fn new(phi: &mut Phi, error: &Error) -> Result<ErrorView, Error> {
+        let keys = phi.events.bindings.keys_label(Action::Confirm);
+        let hint = if keys.is_empty() {
+            "Confirm to go back to the menu".to_string()
+        } else {
+            format!("Press {} to go back to the menu", keys)
+        };
+
+        let mut lines = vec![];
+        for text in &["Something went wrong".to_string(), error.to_string(), hint] {
+            lines.push(try!(phi.ttf_str_sprite(text, FONT, FONT_SIZE, Color::RGB(220, 220, 220))));
+        }
+
+        Ok(ErrorView {
+            lines: lines,
+        })
+    }
+
+    /// Replaces every view with one showing `error`. If even that fails,
+    /// the error is printed and the game quits.
+    pub fn show(phi: &mut Phi, error: Error) -> ViewAction {
+        println!("{}", error);
+        match ErrorView::new(phi, &error) {
+            Ok(view) => ViewAction::Reset(Box::new(view)),
+            Err(e) => {
+                println!("{}", e);
+                ViewAction::Quit
+            },
+        }
+    }
+}
+
+impl View for ErrorView {
+    fn update(&mut self, phi: &mut Phi, _dt: f64) -> ViewAction {
+        if phi.events.now.quit || phi.events.pressed(Action::Pause) {
+            return ViewAction::Quit;
+        }
+
+        if phi.events.pressed(Action::Confirm) {
+            return match ::views::main_menu::MainMenuView::new(phi) {
+                Ok(menu) => ViewAction::Reset(Box::new(menu)),
+                Err(e) => ErrorView::show(phi, e),
+            }
+        }
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _alpha: f64) {
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        let (win_w, win_h) = phi.output_size();
+        let box_y = (win_h - self.lines.len() as f64 * LINE_H) / 2.0;
+
+        for (i, line) in self.lines.iter().enumerate() {
+            let (w, h) = line.size();
+            phi.renderer.copy_sprite(line, Rectangle {
+                x: (win_w - w) / 2.0,
+                y: box_y + LINE_H * i as f64 + (LINE_H - h) / 2.0,
+                w: w,
+                h: h,
+            });
+        }
+    }
+}
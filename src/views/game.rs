@@ -1,49 +1,131 @@
-use ::phi::{Phi, View, ViewAction};
-use ::phi::data::{Rectangle, MaybeAlive};
-use ::phi::gfx::{AnimatedSprite, AnimatedSpriteDescr, Sprite, CopySprite};
+use ::phi::{Error, Phi, View, ViewAction};
+use ::phi::audio::Sound;
+use ::phi::data::{Circle, Rectangle, Shape};
+use ::phi::ecs::{Aim, Charge, Components, Curve, Death, Entity, Gun, Halt, Health, Hitbox, Lifetime, Look, Path, Points, Team,
+                 Transform, Velocity, Visual, World};
+use ::phi::gfx::{AnimatedSprite, Sprite};
+use ::phi::input::Action;
+use ::phi::manifest::{Manifest, SPRITES_PATH};
+use ::views::error::ErrorView;
+use ::views::high_scores::HighScores;
+use ::views::hud::{Hud, Status};
+use ::views::name_entry::NameEntryView;
+use ::views::score::{Score, Subscriber};
 use ::views::shared::BackgroundSet;
 use ::sdl2::pixels::Color;
+use ::std::rc::Rc;
 
 // Constants
 const DEBUG: bool = false;
 
-const PLAYER_SPEED: f64 = 180.0;
-const SHIP_PATH: &'static str = "assets/spaceship.png";
-const SHIP_W: f64 = 43.0;
-const SHIP_H: f64 = 39.0;
+/// The high score table which the scores of a game go into.
+pub const MODE: &'static str = "arcade";
 
-const ASTEROID_PATH: &'static str = "assets/asteroid.png";
-const ASTEROIDS_WIDE: usize = 21;
-const ASTEROIDS_HIGH: usize = 7;
-const ASTEROIDS_TOTAL: usize = ASTEROIDS_WIDE * ASTEROIDS_HIGH - 4;
-const ASTEROID_SIDE: f64 = 96.0;
+const PLAYER_SPEED: f64 = 180.0;
+const PLAYER_START: (f64, f64) = (64.0, 64.0);
+const PLAYER_LIVES: u32 = 3;
+/// How long, in seconds, the ship stays destroyed before coming back.
+const RESPAWN_DELAY: f64 = 1.5;
+/// How long the ship cannot be hit after coming back.
+const INVULNERABLE_TIME: f64 = 2.0;
+/// How many times per second the ship blinks while it cannot be hit.
+const BLINK_RATE: f64 = 8.0;
+
+/// How much of its heat the cannon loses every second.
+const HEAT_COOLING: f64 = 0.35;
+/// Once overheated, how cool the cannon must get before firing again.
+const HEAT_RESUME: f64 = 0.3;
+
+/// How long every wave lasts, in seconds.
+const WAVE_TIME: f64 = 30.0;
+/// The chance for an asteroid to appear every tick during the first wave.
+const ASTEROID_CHANCE: f64 = 0.01;
+/// How much more often asteroids appear with every wave.
+const WAVE_CHANCE_STEP: f64 = 0.25;
+
+const FIRE_SOUND: &'static str = "assets/fire.wav";
+const EXPLOSION_SOUND: &'static str = "assets/explosion.wav";
+const MUSIC: &'static str = "assets/music.wav";
 
 const BULLET_SPEED: f64 = 240.0;
 const BULLET_W: f64 = 8.0;
 const BULLET_H: f64 = 4.0;
 
-const EXPLOSION_PATH: &'static str = "assets/explosion.png";
-const EXPLOSIONS_WIDE: usize = 5;
-const EXPLOSIONS_HIGH: usize = 4;
-const EXPLOSIONS_TOTAL: usize = 17;
-const EXPLOSION_SIDE: f64 = 96.0;
-const EXPLOSION_FPS: f64 = 16.0;
-const EXPLOSION_DURATION: f64 = 1.0 / EXPLOSION_FPS * EXPLOSIONS_TOTAL as f64;
-
+const ENEMY_BULLET_SPEED: f64 = 200.0;
+const ENEMY_BULLET_SIZE: f64 = 6.0;
+
+/// How fast enemies fly in, in pixels per second.
+const ENEMY_SPEED: f64 = 120.0;
+/// The chance for enemies to appear every tick during the first wave.
+const ENEMY_CHANCE: f64 = 0.004;
+const SWEEP_AMPLITUDE: f64 = 60.0;
+const SWEEP_ANGULAR_VEL: f64 = 3.0;
+/// Where, as a part of the window's width, divers charge at the player.
+const DIVE_START: f64 = 0.75;
+const DIVE_SPEED: f64 = 300.0;
+/// Where, as a part of the window's width, turrets stop.
+const TURRET_STOP: f64 = 0.8;
+/// How long turrets stay before moving on, in seconds.
+const TURRET_STAY: f64 = 12.0;
+const FORMATION_AMPLITUDE: f64 = 30.0;
+const FORMATION_ANGULAR_VEL: f64 = 1.5;
+/// The place of every wingman of a formation, in `FORMATION_SPACING`s.
+const FORMATION: [(f64, f64); 5] = [(0.0, 0.0), (1.0, -1.0), (1.0, 1.0), (2.0, -2.0), (2.0, 2.0)];
+const FORMATION_SPACING: f64 = 40.0;
+
+/// How many waves make a stage, at the end of which comes a boss.
+const STAGE_WAVES: u32 = 3;
+const BOSS_HEALTH: u32 = 60;
+const BOSS_POINTS: u64 = 5000;
+const BOSS_SPEED: f64 = 60.0;
+/// How far from the right edge the boss stops.
+const BOSS_MARGIN: f64 = 20.0;
+const BOSS_BOB_AMPLITUDE: f64 = 80.0;
+const BOSS_BOB_ANGULAR_VEL: f64 = 0.8;
+/// How long the boss holds its fire when it comes in or changes phase.
+const BOSS_PHASE_PAUSE: f64 = 1.5;
+/// The parts of the boss, relative to its top left corner, and whether
+/// they are weak points. Everything else is armor.
+const BOSS_PARTS: [(Rectangle, bool); 6] = [
+    (Rectangle { x: 8.0, y: 80.0, w: 36.0, h: 32.0 }, true),
+    (Rectangle { x: 56.0, y: 8.0, w: 40.0, h: 32.0 }, true),
+    (Rectangle { x: 56.0, y: 152.0, w: 40.0, h: 32.0 }, true),
+    (Rectangle { x: 28.0, y: 36.0, w: 130.0, h: 120.0 }, false),
+    (Rectangle { x: 64.0, y: 0.0, w: 86.0, h: 44.0 }, false),
+    (Rectangle { x: 64.0, y: 148.0, w: 86.0, h: 44.0 }, false),
+];
+/// The part which fires bullets, and takes double damage.
+const BOSS_CORE: usize = 0;
+/// The parts which fire lasers.
+const BOSS_PODS: [usize; 2] = [1, 2];
+const SPREAD_BULLETS: usize = 12;
+/// How fast the rings of bullets turn, in radians per second.
+const SPREAD_SPIN: f64 = 0.5;
+const SPREAD_INTERVAL: f64 = 1.6;
+const BURST_SHOTS: u32 = 5;
+const BURST_INTERVAL: f64 = 2.0;
+const BURST_DELAY: f64 = 0.12;
+const LASER_INTERVAL: f64 = 3.5;
+const LASER_CHARGE: f64 = 0.8;
+const LASER_TIME: f64 = 1.0;
+const LASER_H: f64 = 12.0;
+/// How long the boss keeps exploding once destroyed.
+const BOSS_DEATH_TIME: f64 = 2.5;
+const BOSS_BLAST_INTERVAL: f64 = 0.15;
+
+/// The order in which things are drawn, from the bottom up.
+const SHIP_LAYER: i32 = 0;
+const BULLET_LAYER: i32 = 1;
+const ASTEROID_LAYER: i32 = 2;
+const ENEMY_LAYER: i32 = 3;
+const BOSS_LAYER: i32 = 4;
+const ENEMY_BULLET_LAYER: i32 = 5;
+const EXPLOSION_LAYER: i32 = 6;
+
+/// The points for an average asteroid: one with a 64 pixels wide hitbox,
+/// flying at 100 pixels per second.
+const ASTEROID_POINTS: f64 = 100.0;
 
-trait Bullet {
-    /// Update the bullet.
-    /// If the bullet should be destroyed, e.g. because it has left the screen,
-    /// then returns `None`.
-    /// Otherwise, return `Some(update_bullet)`.
-    fn update(self: Box<Self>, phi: &mut Phi, dt: f64) -> Option<Box<Bullet>>;
-
-    /// Render the bullet to the screen.
-    fn render(&self, phi: &mut Phi);
-
-    /// Get the bounding box.
-    fn rect(&self) -> Rectangle;
-}
 
 #[derive(Clone, Copy)]
 enum CannonType {
@@ -52,244 +134,598 @@ enum CannonType {
     DivergentBullet { a: f64, b: f64 },
 }
 
+impl CannonType {
+    fn name(&self) -> &'static str {
+        match *self {
+            CannonType::RectBullet => "Blaster",
+            CannonType::SineBullet { .. } => "Wave Gun",
+            CannonType::DivergentBullet { .. } => "Spreader",
+        }
+    }
+
+    /// The cannon's number, as used by `Action::SelectWeapon`.
+    fn weapon(&self) -> u8 {
+        match *self {
+            CannonType::RectBullet => 1,
+            CannonType::SineBullet { .. } => 2,
+            CannonType::DivergentBullet { .. } => 3,
+        }
+    }
+
+    /// How much hotter the cannon gets with every shot. It cannot fire
+    /// again for a while once this adds up to 1.
+    fn heat(&self) -> f64 {
+        match *self {
+            CannonType::RectBullet => 0.08,
+            CannonType::SineBullet { .. } => 0.12,
+            CannonType::DivergentBullet { .. } => 0.15,
+        }
+    }
+
+    /// How its bullets weave up and down as they fly, if they do.
+    fn curve(&self) -> Option<Curve> {
+        match *self {
+            CannonType::RectBullet => None,
+            CannonType::SineBullet { amplitude, angular_vel } => {
+                Some(Curve::Sine { amplitude: amplitude, angular_vel: angular_vel })
+            },
+            CannonType::DivergentBullet { a, b } => Some(Curve::Cubic { a: a, b: b }),
+        }
+    }
+}
+
 fn bullet_sized_rectangle(x: f64, y: f64) -> Rectangle {
     Rectangle {
         x: x, y: y, w: BULLET_W, h: BULLET_H,
     }
 }
 
-struct DivergentBullet {
-    pos_x: f64,
-    origin_y: f64,
-    a: f64,
-    b: f64,
-    total_time: f64,
+/// Adds a bullet of the player, fired by `cannon`, at `rect` to the world.
+/// It flies to the right, and the cannon decides how it weaves up and down.
+fn spawn_shot(world: &mut World, shots: &mut Components<CannonType>, rect: Rectangle, cannon: CannonType) {
+    let bullet = world.create();
+    world.transforms.insert(bullet, Transform::new(rect));
+    world.velocities.insert(bullet, Velocity { x: BULLET_SPEED, y: 0.0 });
+    world.looks.insert(bullet, Look::new(Visual::Fill(Color::RGB(230, 230, 30)), BULLET_LAYER));
+    world.hitboxes.insert(bullet, Hitbox::new(Shape::Rect(Rectangle::with_size(rect.w, rect.h))));
+    world.teams.insert(bullet, Team::Player);
+    if let Some(curve) = cannon.curve() {
+        world.paths.insert(bullet, Path::new(rect.y, curve));
+    }
+    shots.insert(bullet, cannon);
 }
 
-impl Bullet for DivergentBullet {
-    fn update(mut self: Box<Self>, phi: &mut Phi, dt: f64) -> Option<Box<Bullet>> {
-        self.total_time += dt;
-        self.pos_x += BULLET_SPEED * dt;
+/// Adds a bullet of the enemies to the world, leaving `center` in the
+/// direction of `dir`, a unit vector.
+fn spawn_enemy_bullet(world: &mut World, center: (f64, f64), dir: (f64, f64)) {
+    let radius = ENEMY_BULLET_SIZE / 2.0;
+    let bullet = world.create();
+    world.transforms.insert(bullet, Transform::new(
+        Rectangle::with_size(ENEMY_BULLET_SIZE, ENEMY_BULLET_SIZE).center_at(center)));
+    world.velocities.insert(bullet, Velocity { x: dir.0 * ENEMY_BULLET_SPEED, y: dir.1 * ENEMY_BULLET_SPEED });
+    world.looks.insert(bullet, Look::new(Visual::Fill(Color::RGB(255, 90, 60)), ENEMY_BULLET_LAYER));
+    world.hitboxes.insert(bullet, Hitbox::new(Shape::Circle(Circle { x: radius, y: radius, r: radius })));
+    world.teams.insert(bullet, Team::Enemy);
+}
 
-        let (w, h) = phi.output_size();
-        let rect = self.rect();
+// Data Types
 
-        if rect.x > w || rect.x < 0.0 || rect.y > h || rect.y < 0.0 {
-            None
-        } else {
-            Some(self)
-        }
-    }
+struct ExplosionFactory {
+    sprite: AnimatedSprite,
+}
 
-    fn render(&self, phi: &mut Phi) {
-         phi.renderer.set_draw_color(Color::RGB(230, 230, 30));
-         phi.renderer.fill_rect(self.rect().to_sdl().unwrap());
+impl ExplosionFactory {
+    fn new(phi: &mut Phi, manifest: &Manifest) -> Result<ExplosionFactory, Error> {
+        Ok(ExplosionFactory {
+            sprite: try!(manifest.animation(phi, "explosion"))
+        })
     }
 
-    fn rect(&self) -> Rectangle {
-        let dy = self.a * (
-            (self.total_time / self.b).powi(3) -
-            (self.total_time / self.b).powi(2));
-
-        bullet_sized_rectangle(self.pos_x, self.origin_y + dy)
+    /// Adds an explosion around `center` to the world, which lasts as long
+    /// as its animation.
+    fn at_center(&self, world: &mut World, center: (f64, f64)) {
+        let sprite = self.sprite.clone();
+        let (w, h) = sprite.size();
+        let explosion = world.create();
+        world.transforms.insert(explosion, Transform::new(Rectangle::with_size(w, h).center_at(center)));
+        world.lifetimes.insert(explosion, Lifetime(sprite.duration()));
+        world.looks.insert(explosion, Look::new(Visual::Animation(sprite), EXPLOSION_LAYER));
     }
 }
 
-struct SineBullet {
-    pos_x: f64,
-    origin_y: f64,
-    amplitude: f64,
-    angular_vel: f64,
-    total_time: f64,
+struct AsteroidFactory {
+    sprite: AnimatedSprite,
+    hitbox: Rectangle,
+    /// The shape of every frame of `sprite`.
+    shapes: Rc<Vec<Shape>>,
 }
 
-impl Bullet for SineBullet {
-    fn update(mut self: Box<Self>, phi: &mut Phi, dt: f64) -> Option<Box<Bullet>> {
-        self.total_time += dt;
-        self.pos_x += BULLET_SPEED * dt;
-        let (w, _) = phi.output_size();
-        if self.rect().x > w {
-            None
-        } else {
-            Some(self)
-        }
+impl AsteroidFactory {
+    fn new(phi: &mut Phi, manifest: &Manifest) -> Result<AsteroidFactory, Error> {
+        Ok(AsteroidFactory {
+            sprite: try!(manifest.animation(phi, "asteroid")),
+            hitbox: try!(manifest.hitbox("asteroid")),
+            shapes: Rc::new(try!(manifest.shapes(phi, "asteroid"))),
+        })
     }
 
-    fn render(&self, phi: &mut Phi) {
-        phi.renderer.set_draw_color(Color::RGB(230, 230, 30));
-        phi.renderer.fill_rect(self.rect().to_sdl().unwrap());
+    /// Adds an asteroid coming in from the right to the world.
+    fn random(&self, phi: &mut Phi, world: &mut World) {
+        let (w, h) = phi.output_size();
+        let mut sprite = self.sprite.clone();
+        sprite.set_fps(phi.rng.range(10.0, 30.0));
+        let (side_w, side_h) = sprite.size();
+        let rect = Rectangle::with_size(side_w, side_h)
+            .moved(w, phi.rng.range(0.0, h - side_h));
+        let vel = phi.rng.range(50.0, 150.0);
+
+        let asteroid = world.create();
+        world.transforms.insert(asteroid, Transform::new(rect));
+        world.velocities.insert(asteroid, Velocity { x: -vel, y: 0.0 });
+        world.looks.insert(asteroid, Look::new(Visual::Animation(sprite), ASTEROID_LAYER));
+        world.hitboxes.insert(asteroid, Hitbox::frames(self.shapes.clone()));
+        world.health.insert(asteroid, Health(1));
+        world.teams.insert(asteroid, Team::Neutral);
+        world.points.insert(asteroid, Points(self.points(vel)));
     }
 
-    fn rect(&self) -> Rectangle {
-        let dy = self.amplitude * f64::sin(self.angular_vel * self.total_time);
-        bullet_sized_rectangle(self.pos_x, self.origin_y + dy)
+    /// What destroying an asteroid flying at `vel` is worth: bigger and
+    /// faster ones give more points.
+    fn points(&self, vel: f64) -> u64 {
+        let size = (self.hitbox.w * self.hitbox.h).sqrt() / 64.0;
+        let speed = vel / 100.0;
+        ((ASTEROID_POINTS * size * speed) / 10.0).round() as u64 * 10
     }
 }
 
-
-#[derive(Clone, Copy)]
-struct RectBullet {
-    rect: Rectangle,
+#[derive(Clone, Copy, PartialEq)]
+enum EnemyKind {
+    /// Crosses the screen weaving up and down.
+    Sweeper,
+    /// Flies in, then charges at the player.
+    Diver,
+    /// Flies in a V formation with others of its kind.
+    Wingman,
+    /// Stops near the right edge for a while, firing at the player.
+    Turret,
 }
 
-impl Bullet for RectBullet {
+const ENEMY_KINDS: [EnemyKind; 4] = [
+    EnemyKind::Sweeper,
+    EnemyKind::Diver,
+    EnemyKind::Wingman,
+    EnemyKind::Turret,
+];
+
+impl EnemyKind {
+    /// Its frame in the `enemy` sheet.
+    fn frame(self) -> usize {
+        self as usize
+    }
 
-    fn update(mut self: Box<Self>, phi: &mut Phi, dt: f64) -> Option<Box<Bullet>> {
-        let (w, _) = phi.output_size();
-        self.rect.x += BULLET_SPEED * dt;
+    /// How many bullets it takes.
+    fn health(self) -> u32 {
+        match self {
+            EnemyKind::Turret => 3,
+            _ => 1,
+        }
+    }
 
-        if self.rect.x > w {
-            None
-        } else {
-            Some(self)
+    fn points(self) -> u64 {
+        match self {
+            EnemyKind::Sweeper => 150,
+            EnemyKind::Diver => 200,
+            EnemyKind::Wingman => 100,
+            EnemyKind::Turret => 400,
         }
     }
 
-    fn render(&self, phi: &mut Phi) {
-        phi.renderer.set_draw_color(Color::RGB(230, 230, 30));
-        phi.renderer.fill_rect(self.rect.to_sdl().unwrap());
+    /// How often it fires, in seconds, if it does.
+    fn fire_interval(self) -> Option<f64> {
+        match self {
+            EnemyKind::Sweeper => Some(2.0),
+            EnemyKind::Diver => None,
+            EnemyKind::Wingman => Some(3.0),
+            EnemyKind::Turret => Some(1.2),
+        }
     }
 
-    fn rect(&self) -> Rectangle {
-        self.rect
+    /// Gives `enemy`, coming in from the right of a window `window_w`
+    /// wide, the components which make it fly and fire the way its kind
+    /// does. Its gun first fires in `fire_in` seconds.
+    fn equip(self, world: &mut World, enemy: Entity, window_w: f64, fire_in: f64) {
+        let rect = match world.transforms.get(enemy) {
+            Some(transform) => transform.rect,
+            None => return,
+        };
+        world.velocities.insert(enemy, Velocity { x: -ENEMY_SPEED, y: 0.0 });
+
+        match self {
+            EnemyKind::Sweeper => world.paths.insert(enemy, Path::new(rect.y, Curve::Sine {
+                amplitude: SWEEP_AMPLITUDE,
+                angular_vel: SWEEP_ANGULAR_VEL,
+            })),
+            EnemyKind::Diver => world.charges.insert(enemy, Charge { at_x: window_w * DIVE_START, speed: DIVE_SPEED }),
+            EnemyKind::Wingman => world.paths.insert(enemy, Path::new(rect.y, Curve::Sine {
+                amplitude: FORMATION_AMPLITUDE,
+                angular_vel: FORMATION_ANGULAR_VEL,
+            })),
+            EnemyKind::Turret => world.halts.insert(enemy, Halt {
+                at_x: window_w * TURRET_STOP,
+                left: TURRET_STAY,
+                speed: ENEMY_SPEED,
+            }),
+        }
+
+        if let Some(interval) = self.fire_interval() {
+            // turrets aim at the player, the others fire straight ahead
+            let aim = match self {
+                EnemyKind::Turret => Aim::Target(-1.0, 0.0),
+                _ => Aim::Fixed(-1.0, 0.0),
+            };
+            world.guns.insert(enemy, Gun::new(aim, (rect.w / 2.0, rect.h / 2.0), interval, fire_in));
+        }
     }
 }
 
-// Data Types
+struct EnemyFactory {
+    sprites: Vec<Sprite>,
+    hitbox: Rectangle,
+}
 
-struct Asteroid {
-    sprite: AnimatedSprite,
-    rect: Rectangle,
-    vel: f64,
+impl EnemyFactory {
+    fn new(phi: &mut Phi, manifest: &Manifest) -> Result<EnemyFactory, Error> {
+        Ok(EnemyFactory {
+            sprites: try!(manifest.frames(phi, "enemy")),
+            hitbox: try!(manifest.hitbox("enemy")),
+        })
+    }
+
+    /// Adds a random kind of enemy, or a whole formation of them, coming in
+    /// from the right to the world.
+    fn random(&self, phi: &mut Phi, world: &mut World) {
+        let kind = *phi.rng.pick(&ENEMY_KINDS).unwrap();
+        let (w, h) = phi.output_size();
+        let sprite = &self.sprites[kind.frame()];
+        let (enemy_w, enemy_h) = sprite.size();
+
+        // leave room for whatever the enemy moves around its origin
+        let room = match kind {
+            EnemyKind::Sweeper => SWEEP_AMPLITUDE,
+            EnemyKind::Wingman => FORMATION_AMPLITUDE + FORMATION_SPACING * 2.0,
+            _ => 0.0,
+        };
+        let origin_y = phi.rng.range(room, (h - enemy_h - room).max(room));
+
+        let offsets: &[(f64, f64)] = match kind {
+            EnemyKind::Wingman => &FORMATION,
+            _ => &[(0.0, 0.0)],
+        };
+
+        for &(dx, dy) in offsets {
+            let rect = Rectangle::with_size(enemy_w, enemy_h)
+                .moved(w + dx * FORMATION_SPACING, origin_y + dy * FORMATION_SPACING);
+
+            let enemy = world.create();
+            world.transforms.insert(enemy, Transform::new(rect));
+            world.looks.insert(enemy, Look::new(Visual::Sprite(sprite.clone()), ENEMY_LAYER));
+            world.hitboxes.insert(enemy, Hitbox::new(Shape::Rect(self.hitbox)));
+            world.health.insert(enemy, Health(kind.health()));
+            world.teams.insert(enemy, Team::Enemy);
+            world.points.insert(enemy, Points(kind.points()));
+            kind.equip(world, enemy, w, phi.rng.range(0.5, kind.fire_interval().unwrap_or(1.0)));
+        }
+    }
 }
 
-impl Asteroid {
+#[derive(Clone, Copy, PartialEq)]
+enum BossPhase {
+    /// Rings of bullets in every direction.
+    Spread,
+    /// Quick bursts of bullets aimed at the player.
+    Burst,
+    /// Lasers across the screen from both pods.
+    Laser,
+}
 
-    fn asteroid_rect(x: f64, y: f64) -> Rectangle {
-        Rectangle {
-            w: ASTEROID_SIDE,
-            h: ASTEROID_SIDE,
-            x: x,
-            y: y,
+/// What a bullet hitting the boss did.
+#[derive(Clone, Copy, PartialEq)]
+enum BossHit {
+    Miss,
+    /// The bullet was stopped by the armor.
+    Armor,
+    Weak,
+    /// The boss was weakened enough to change its pattern.
+    PhaseChange,
+    Destroyed,
+}
+
+/// What a bullet of the player hit.
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    Entity(Entity),
+    Boss,
+}
+
+/// A laser fired from one of the parts of the boss, at its height, across
+/// the whole screen. It only hurts once it has charged.
+struct Laser {
+    part: usize,
+    charge: f64,
+    left: f64,
+}
+
+impl BossPhase {
+    /// The gun of the core during the phase, which holds its fire for a
+    /// while first.
+    fn gun(self) -> Gun {
+        let muzzle = BOSS_PARTS[BOSS_CORE].0.center();
+        match self {
+            BossPhase::Spread => {
+                let aim = Aim::Ring { bullets: SPREAD_BULLETS, spin: SPREAD_SPIN };
+                Gun::new(aim, muzzle, SPREAD_INTERVAL, BOSS_PHASE_PAUSE)
+            },
+            BossPhase::Burst => {
+                Gun::new(Aim::Target(-1.0, 0.0), muzzle, BURST_INTERVAL, BOSS_PHASE_PAUSE)
+                    .bursts(BURST_SHOTS, BURST_DELAY)
+            },
+            BossPhase::Laser => Gun::new(Aim::Target(-1.0, 0.0), muzzle, LASER_INTERVAL, BOSS_PHASE_PAUSE),
         }
     }
+}
 
-    fn update(mut self, dt: f64) -> Option<Asteroid> {
-        self.rect.x -= dt * self.vel;
-        self.sprite.add_time(dt);
-        if self.rect.x <= -ASTEROID_SIDE {
-            None
-        } else {
-            Some(self)
+/// The parts of the boss, its phase and its lasers. It flies in, bobs up
+/// and down and fires through its components in the world, and its phase
+/// decides which gun it has.
+struct Boss {
+    entity: Entity,
+    health: u32,
+    phase: BossPhase,
+    /// How long until the pods fire their lasers, during the laser phase.
+    laser_in: f64,
+    lasers: Vec<Laser>,
+    /// Once destroyed, how long it keeps exploding.
+    dying: Option<f64>,
+    blast_in: f64,
+}
+
+impl Boss {
+    /// A boss coming in from the right of `window`.
+    fn new(world: &mut World, sprite: Sprite, window: (f64, f64)) -> Boss {
+        let (w, h) = sprite.size();
+        let rect = Rectangle::with_size(w, h).moved(window.0, (window.1 - h) / 2.0);
+
+        let entity = world.create();
+        world.transforms.insert(entity, Transform::new(rect));
+        world.velocities.insert(entity, Velocity { x: -BOSS_SPEED, y: 0.0 });
+        world.halts.insert(entity, Halt {
+            at_x: window.0 - w - BOSS_MARGIN,
+            left: ::std::f64::INFINITY,
+            speed: BOSS_SPEED,
+        });
+        world.looks.insert(entity, Look::new(Visual::Sprite(sprite), BOSS_LAYER));
+
+        Boss {
+            entity: entity,
+            health: BOSS_HEALTH,
+            phase: BossPhase::Spread,
+            laser_in: BOSS_PHASE_PAUSE,
+            lasers: vec![],
+            dying: None,
+            blast_in: 0.0,
         }
     }
 
-    fn render(&self, phi: &mut Phi) {
-        if DEBUG {
-            phi.renderer.set_draw_color(Color::RGB(200, 200, 50));
-            phi.renderer.fill_rect(self.rect().to_sdl().unwrap());
+    fn transform(&self, world: &World) -> Transform {
+        *world.transforms.get(self.entity).expect("the boss is only destroyed along with it")
+    }
+
+    /// The `i`th of `BOSS_PARTS`, where it currently is.
+    fn part(&self, world: &World, i: usize) -> Rectangle {
+        let rect = self.transform(world).rect;
+        BOSS_PARTS[i].0.moved_by(rect.x, rect.y)
+    }
+
+    /// How much of its health it has left, between 0 and 1.
+    fn health_left(&self) -> f64 {
+        self.health as f64 / BOSS_HEALTH as f64
+    }
+
+    fn phase_for(health_left: f64) -> BossPhase {
+        if health_left > 2.0 / 3.0 {
+            BossPhase::Spread
+        } else if health_left > 1.0 / 3.0 {
+            BossPhase::Burst
+        } else {
+            BossPhase::Laser
         }
-        phi.renderer.copy_sprite(&self.sprite, self.rect);
     }
 
-    fn rect(&self) -> Rectangle {
-        self.rect
+    /// Whether it stopped where it fights, and bobs up and down there.
+    fn in_place(&self, world: &World) -> bool {
+        world.paths.get(self.entity).is_some()
     }
 
-    fn sprite_descr() -> AnimatedSpriteDescr<'static> {
-        AnimatedSpriteDescr {
-            image_path: ASTEROID_PATH,
-            total_frames: ASTEROIDS_TOTAL,
-            frames_high: ASTEROIDS_HIGH,
-            frames_wide: ASTEROIDS_WIDE,
-            frame_w: ASTEROID_SIDE,
-            frame_h: ASTEROID_SIDE,
+    /// Gives the boss the gun of its phase, once it is in place.
+    fn arm(&mut self, world: &mut World) {
+        if self.in_place(world) {
+            world.guns.insert(self.entity, self.phase.gun());
+            self.laser_in = BOSS_PHASE_PAUSE;
         }
+        self.lasers.clear();
     }
 
-    fn factory(phi: &mut Phi) -> AsteroidFactory {
-        AsteroidFactory {
-            sprite: AnimatedSprite::load_frames_with_fps(phi, 1.0, Self::sprite_descr()),
+    /// The part which `bullet` reached first during this tick, and when.
+    fn part_hit(&self, world: &World, bullet: Entity) -> Option<(f64, usize)> {
+        if self.dying.is_some() {
+            return None;
         }
+
+        // on a tie, the weak points come first
+        (0..BOSS_PARTS.len())
+            .filter_map(|i| world.sweep(bullet, &Shape::Rect(self.part(world, i)), (0.0, 0.0)).map(|time| (time, i)))
+            .fold(None, |first, hit| match first {
+                Some(first) if first.0 <= hit.0 => Some(first),
+                _ => Some(hit),
+            })
     }
-}
 
-struct Explosion {
-    sprite: AnimatedSprite,
-    rect: Rectangle,
-    alive_since: f64,
-}
+    /// Lets `bullet` hit the first part of the boss in its way. Weak points
+    /// take the damage, the core twice as much; the armor only stops
+    /// bullets. Once destroyed, its death is kept with the others of the
+    /// world, and it stays where it is while it explodes.
+    fn take_hit(&mut self, world: &mut World, bullet: Entity) -> BossHit {
+        let i = match self.part_hit(world, bullet) {
+            Some((_, i)) => i,
+            None => return BossHit::Miss,
+        };
+
+        if !BOSS_PARTS[i].1 {
+            return BossHit::Armor;
+        }
 
-impl Explosion {
+        let damage = if i == BOSS_CORE { 2 } else { 1 };
+        self.health = self.health.saturating_sub(damage);
+
+        if self.health == 0 {
+            self.dying = Some(BOSS_DEATH_TIME);
+            self.lasers.clear();
+            world.guns.remove(self.entity);
+            world.paths.remove(self.entity);
+            world.halts.remove(self.entity);
+            world.velocities.insert(self.entity, Velocity { x: 0.0, y: 0.0 });
+            world.deaths.push(Death {
+                entity: self.entity,
+                by: bullet,
+                center: self.transform(world).rect.center(),
+                points: BOSS_POINTS,
+            });
+            return BossHit::Destroyed;
+        }
 
-    fn update(mut self, dt: f64) -> Option<Explosion> {
-        self.alive_since += dt;
-        self.sprite.add_time(dt);
-        if self.alive_since >= EXPLOSION_DURATION {
-            None
-        } else {
-            Some(self)
+        let phase = Boss::phase_for(self.health_left());
+        if phase != self.phase {
+            self.phase = phase;
+            self.arm(world);
+            return BossHit::PhaseChange;
         }
+        BossHit::Weak
     }
 
-    fn render(&self, phi: &mut Phi) {
-        phi.renderer.copy_sprite(&self.sprite, self.rect);
+    /// Whether `shape`, placed at `at`, touches any part of the boss.
+    fn overlaps(&self, world: &World, shape: &Shape, at: (f64, f64)) -> bool {
+        self.dying.is_none() &&
+            (0..BOSS_PARTS.len()).any(|i| shape.overlaps(at, &Shape::Rect(self.part(world, i)), (0.0, 0.0)))
     }
 
-    fn sprite_descr() -> AnimatedSpriteDescr<'static> {
-        AnimatedSpriteDescr {
-            image_path: EXPLOSION_PATH,
-            total_frames: EXPLOSIONS_TOTAL,
-            frames_high: EXPLOSIONS_HIGH,
-            frames_wide: EXPLOSIONS_WIDE,
-            frame_w: EXPLOSION_SIDE,
-            frame_h: EXPLOSION_SIDE
+    fn laser_rect(&self, world: &World, laser: &Laser) -> Rectangle {
+        let part = self.part(world, laser.part);
+        Rectangle {
+            x: 0.0,
+            y: part.center().1 - LASER_H / 2.0,
+            w: part.x,
+            h: LASER_H,
         }
     }
 
-    fn factory(phi: &mut Phi) -> ExplosionFactory {
-        ExplosionFactory {
-            sprite: AnimatedSprite::load_frames_with_fps(phi, EXPLOSION_FPS, Self::sprite_descr())
-        }
+    /// Whether a charged laser goes through `shape`, placed at `at`.
+    fn laser_hits(&self, world: &World, shape: &Shape, at: (f64, f64)) -> bool {
+        self.lasers.iter().any(|laser| {
+            laser.charge <= 0.0 && shape.overlaps(at, &Shape::Rect(self.laser_rect(world, laser)), (0.0, 0.0))
+        })
     }
-}
 
-struct ExplosionFactory {
-    sprite: AnimatedSprite,
-}
+    /// The boss system: once the boss has stopped, it bobs up and down and
+    /// is armed for its phase, and fires its lasers during the last one.
+    /// Once destroyed, it fills `blasts` with where explosions go for a
+    /// while, and then returns `false`.
+    fn update(&mut self, phi: &mut Phi, world: &mut World, dt: f64, blasts: &mut Vec<(f64, f64)>) -> bool {
+        let rect = self.transform(world).rect;
+
+        // explosions all over the boss, then a few at once in its middle
+        if let Some(left) = self.dying {
+            self.blast_in -= dt;
+            if self.blast_in <= 0.0 {
+                self.blast_in += BOSS_BLAST_INTERVAL;
+                let x = phi.rng.range(rect.x, rect.x + rect.w);
+                let y = phi.rng.range(rect.y, rect.y + rect.h);
+                blasts.push((x, y));
+            }
 
-impl ExplosionFactory {
-    fn at_center(&self, center: (f64, f64)) -> Explosion {
-        let mut sprite = self.sprite.clone();
-        Explosion {
-            sprite: sprite,
-            rect: Rectangle::with_size(EXPLOSION_SIDE, EXPLOSION_SIDE).center_at(center),
-            alive_since: 0.0
+            if left <= dt {
+                let (x, y) = rect.center();
+                for &(dx, dy) in &[(0.0, 0.0), (-40.0, -40.0), (40.0, -40.0), (-40.0, 40.0), (40.0, 40.0)] {
+                    blasts.push((x + dx, y + dy));
+                }
+                return false;
+            }
+            self.dying = Some(left - dt);
+            return true;
         }
-    }
-}
 
-fn rand<T: ::rand::Rand>() -> T {
-    ::rand::random::<T>()
-}
+        if !self.in_place(world) {
+            let stopped = world.halts.get(self.entity).map_or(false, |halt| rect.x <= halt.at_x);
+            if !stopped {
+                return true;
+            }
 
-fn randf64() -> f64 {
-    rand::<f64>().abs()
-}
+            let window = phi.output_size();
+            let amplitude = BOSS_BOB_AMPLITUDE.min((window.1 - rect.h) / 2.0).max(0.0);
+            world.paths.insert(self.entity, Path::new((window.1 - rect.h) / 2.0, Curve::Sine {
+                amplitude: amplitude,
+                angular_vel: BOSS_BOB_ANGULAR_VEL,
+            }));
+            self.arm(world);
+        }
 
-struct AsteroidFactory {
-    sprite: AnimatedSprite
-}
+        for laser in &mut self.lasers {
+            if laser.charge > 0.0 {
+                laser.charge -= dt;
+            } else {
+                laser.left -= dt;
+            }
+        }
+        self.lasers.retain(|laser| laser.left > 0.0);
+
+        if self.phase == BossPhase::Laser {
+            self.laser_in -= dt;
+            if self.laser_in <= 0.0 {
+                self.laser_in += LASER_INTERVAL;
+                for &part in &BOSS_PODS {
+                    self.lasers.push(Laser { part: part, charge: LASER_CHARGE, left: LASER_TIME });
+                }
+            }
+        }
 
-impl AsteroidFactory {
-    fn random(&self, phi: &mut Phi) -> Asteroid {
-        let (w, h) = phi.output_size();
-        let mut sprite = self.sprite.clone();
-        sprite.set_fps(randf64() * 20.0 + 10.0);
-        Asteroid {
-            sprite: sprite,
-            rect: Asteroid::asteroid_rect(w, randf64() * (h - ASTEROID_SIDE)),
-            vel: randf64() * 100.0 + 50.0
+        true
+    }
+
+    /// Draws the lasers on top of everything else.
+    fn render(&self, phi: &mut Phi, world: &World, alpha: f64) {
+        if DEBUG {
+            let rect = self.transform(world).at(alpha);
+            for &(part, weak) in &BOSS_PARTS {
+                phi.renderer.set_draw_color(if weak {
+                    Color::RGBA(250, 60, 60, 120)
+                } else {
+                    Color::RGBA(200, 200, 50, 120)
+                });
+                phi.renderer.fill_rect(part.moved_by(rect.x, rect.y));
+            }
+        }
+
+        for laser in &self.lasers {
+            let beam = self.laser_rect(world, laser);
+            if laser.charge > 0.0 {
+                // a thin line shows where the laser is about to go
+                phi.renderer.set_draw_color(Color::RGBA(255, 80, 80, 140));
+                phi.renderer.fill_rect(Rectangle { y: beam.y + beam.h / 2.0 - 1.0, h: 2.0, .. beam });
+            } else {
+                phi.renderer.set_draw_color(Color::RGB(255, 60, 60));
+                phi.renderer.fill_rect(beam);
+                phi.renderer.set_draw_color(Color::RGB(255, 220, 220));
+                phi.renderer.fill_rect(Rectangle { y: beam.y + beam.h / 3.0, h: beam.h / 3.0, .. beam });
+            }
         }
     }
 }
@@ -324,142 +760,215 @@ impl ShipFrame {
     }
 }
 
+/// The state of the player's ship. Where it is, how it looks and what it
+/// collides with are in the world, as `entity`.
 struct Ship {
-    rect: Rectangle,
+    entity: Entity,
     sprites: Vec<Sprite>,
     current: ShipFrame,
     cannon: CannonType,
+    /// How long the ship still cannot be hit, in seconds.
+    invulnerable: f64,
+    /// How hot the cannon is, between 0 and 1.
+    heat: f64,
+    overheated: bool,
 }
 
 impl Ship {
-    fn spawn_bullets(&self) -> Vec<Box<Bullet>> {
-        let cannons_x = self.rect.x + 30.0;
-        let cannon1_y = self.rect.y + 6.0;
-        let cannon2_y = self.rect.y + SHIP_H - 10.0;
-
-        match self.cannon {
-            CannonType::RectBullet => vec![
-                Box::new(RectBullet {
-                    rect: bullet_sized_rectangle(cannons_x, cannon1_y)
-                }),
-                Box::new(RectBullet {
-                    rect: bullet_sized_rectangle(cannons_x, cannon2_y)
-                })
-            ],
-            CannonType::SineBullet { amplitude, angular_vel } => vec![
-                Box::new(SineBullet {
-                    amplitude: amplitude,
-                    angular_vel: angular_vel,
-                    pos_x: cannons_x,
-                    origin_y: cannon1_y,
-                    total_time: 0.0,
-                }),
-                Box::new(SineBullet {
-                    amplitude: amplitude,
-                    angular_vel: angular_vel,
-                    pos_x: cannons_x,
-                    origin_y: cannon2_y,
-                    total_time: 0.0,
-                })
-            ],
-            CannonType::DivergentBullet { a, b } => vec![
-                Box::new(DivergentBullet {
-                    a: -a,
-                    b: b,
-                    pos_x: cannons_x,
-                    origin_y: cannon1_y,
-                    total_time: 0.0,
-                }),
-                Box::new(DivergentBullet {
-                    a: a,
-                    b: b,
-                    pos_x: cannons_x,
-                    origin_y: cannon2_y,
-                    total_time: 0.0,
-                })
-            ]
+    /// Brings the ship back to where it started, safe for a little while.
+    fn respawn(&mut self, world: &mut World) {
+        if let Some(transform) = world.transforms.get_mut(self.entity) {
+            *transform = Transform::new(transform.rect.moved(PLAYER_START.0, PLAYER_START.1));
+        }
+        self.current = ShipFrame::MidNorm;
+        self.invulnerable = INVULNERABLE_TIME;
+        self.heat = 0.0;
+        self.overheated = false;
+    }
+
+    fn cool_down(&mut self, dt: f64) {
+        self.heat = (self.heat - HEAT_COOLING * dt).max(0.0);
+        if self.overheated && self.heat <= HEAT_RESUME {
+            self.overheated = false;
         }
+    }
+
+    /// Fires the cannon of the ship at `rect`, unless it is too hot.
+    fn fire(&mut self, rect: Rectangle) -> Vec<(Rectangle, CannonType)> {
+        if self.overheated {
+            return vec![];
+        }
+
+        self.heat += self.cannon.heat();
+        if self.heat >= 1.0 {
+            self.heat = 1.0;
+            self.overheated = true;
+        }
+        self.spawn_bullets(rect)
+    }
 
+    fn can_be_hit(&self) -> bool {
+        self.invulnerable <= 0.0
     }
 
+    /// Whether the ship is drawn this frame, as it blinks while it cannot
+    /// be hit.
+    fn is_visible(&self) -> bool {
+        self.can_be_hit() || (self.invulnerable * BLINK_RATE * 2.0) as u32 % 2 == 1
+    }
 
+    fn spawn_bullets(&self, rect: Rectangle) -> Vec<(Rectangle, CannonType)> {
+        let cannons_x = rect.x + 30.0;
+        let cannon1_y = rect.y + 6.0;
+        let cannon2_y = rect.y + rect.h - 10.0;
+
+        // the bullets of the spreader go up from the top cannon
+        let top_cannon = match self.cannon {
+            CannonType::DivergentBullet { a, b } => CannonType::DivergentBullet { a: -a, b: b },
+            cannon => cannon,
+        };
+
+        vec![
+            (bullet_sized_rectangle(cannons_x, cannon1_y), top_cannon),
+            (bullet_sized_rectangle(cannons_x, cannon2_y), self.cannon),
+        ]
+    }
 }
 
 
 // View definition
 
 pub struct GameView {
+    /// The ship, the bullets, the asteroids, the enemies, the boss and the
+    /// explosions.
+    world: World,
     player: Ship,
-    bullets: Vec<Box<Bullet>>,
+    /// The cannon which fired every bullet of the player.
+    shots: Components<CannonType>,
     asteroid_factory: AsteroidFactory,
-    asteroids: Vec<Asteroid>,
     explosion_factory: ExplosionFactory,
-    explosions: Vec<Explosion>,
+    enemy_factory: EnemyFactory,
+    boss_sprite: Sprite,
+    boss: Option<Boss>,
+    /// What every bullet hit first during this tick, and when.
+    first_hits: Components<(f64, Target)>,
     bgs: BackgroundSet,
+    fire_sound: Sound,
+    explosion_sound: Sound,
+    music: Sound,
+    /// How many ships the player has left, including the current one.
+    lives: u32,
+    /// While the ship is destroyed, how long until it comes back.
+    respawn_in: Option<f64>,
+    score: Score,
+    /// The high scores as of the start of the game, so that the ticks do
+    /// not wait on the disk.
+    high_scores: HighScores,
+    /// What the hud follows of the score.
+    hud_scores: Subscriber,
+    hud: Hud,
+    wave: u32,
+    /// How long until the next wave.
+    wave_left: f64,
 }
 
 impl GameView {
-    pub fn new(phi: &mut Phi, bgs: BackgroundSet) -> GameView {
-        let spritesheet = Sprite::load(&mut phi.renderer, SHIP_PATH).unwrap();
-
-        let mut sprites = Vec::with_capacity(9);
-
-        for y in 0..3 {
-            for x in 0..3 {
-                sprites.push(spritesheet.region(Rectangle {
-                    w: SHIP_W,
-                    h: SHIP_H,
-                    x: SHIP_W * x as f64,
-                    y: SHIP_H * y as f64,
-                }).unwrap());
-            }
-        }
-
-        GameView {
+    pub fn new(phi: &mut Phi, bgs: BackgroundSet) -> Result<GameView, Error> {
+        let manifest = try!(Manifest::load(SPRITES_PATH));
+        let sprites = try!(manifest.frames(phi, "spaceship"));
+        let (ship_w, ship_h) = sprites[ShipFrame::MidNorm as usize].size();
+        let hud = try!(Hud::new(phi, sprites[ShipFrame::MidNorm as usize].clone()));
+
+        let player_rect = Rectangle {
+            x: PLAYER_START.0,
+            y: PLAYER_START.1,
+            w: ship_w,
+            h: ship_h
+        };
+
+        let mut score = Score::new();
+        let hud_scores = score.subscribe();
+
+        let mut world = World::new();
+        let ship = world.create();
+        let mut hitbox = Hitbox::frames(Rc::new(try!(manifest.shapes(phi, "spaceship"))));
+        hitbox.frame = ShipFrame::MidNorm as usize;
+        world.transforms.insert(ship, Transform::new(player_rect));
+        world.looks.insert(ship, Look::new(Visual::Sprite(sprites[ShipFrame::MidNorm as usize].clone()), SHIP_LAYER));
+        world.hitboxes.insert(ship, hitbox);
+        world.teams.insert(ship, Team::Player);
+
+        Ok(GameView {
+            world: world,
             player: Ship {
-                rect: Rectangle {
-                    x: 64.0,
-                    y: 64.0,
-                    w: SHIP_W,
-                    h: SHIP_H
-                },
+                entity: ship,
                 sprites: sprites,
                 current: ShipFrame::MidNorm,
                 cannon: CannonType::RectBullet,
+                invulnerable: 0.0,
+                heat: 0.0,
+                overheated: false,
             },
-            asteroid_factory: Asteroid::factory(phi),
-            asteroids: vec![],
-            explosion_factory: Explosion::factory(phi),
-            explosions: vec![],
-            bullets: vec![],
+            shots: Components::new(),
+            asteroid_factory: try!(AsteroidFactory::new(phi, &manifest)),
+            explosion_factory: try!(ExplosionFactory::new(phi, &manifest)),
+            enemy_factory: try!(EnemyFactory::new(phi, &manifest)),
+            boss_sprite: try!(manifest.frames(phi, "boss"))[0].clone(),
+            boss: None,
+            first_hits: Components::new(),
             bgs: bgs,
-        }
+            fire_sound: try!(phi.audio.load(FIRE_SOUND)),
+            explosion_sound: try!(phi.audio.load(EXPLOSION_SOUND)),
+            music: try!(phi.audio.load(MUSIC)),
+            lives: PLAYER_LIVES,
+            respawn_in: None,
+            score: score,
+            high_scores: HighScores::load(),
+            hud_scores: hud_scores,
+            hud: hud,
+            wave: 1,
+            wave_left: WAVE_TIME,
+        })
     }
 
-    fn get_distance_moved(elapsed: f64, diagonal: bool) -> f64 {
-        let moved = if diagonal { 1.0 / 2.0f64.sqrt() } else { 1.0 };
-        moved * PLAYER_SPEED * elapsed
+    fn player_alive(&self) -> bool {
+        self.respawn_in.is_none()
     }
 
-    fn dx_dy(phi: &mut Phi, elapsed: f64) -> (f64, f64) {
-
-        let moved = Self::get_distance_moved(
-            elapsed,
-            phi.events.is_key_diagonal());
+    /// Where the ship is. It is never destroyed, only hidden while it is
+    /// away.
+    fn player_rect(&self) -> Rectangle {
+        self.world.transforms.get(self.player.entity).expect("the ship is never destroyed").rect
+    }
 
-        (
-            Self::d_distance(phi.events.key_left, phi.events.key_right, moved),
-            Self::d_distance(phi.events.key_up, phi.events.key_down, moved)
-        )
+    /// Forgets about the entities which were destroyed. Until then, the
+    /// game still knows which cannon fired the bullets which were spent.
+    fn forget_destroyed(&mut self) {
+        self.world.retain_alive(&mut self.shots);
     }
 
-    /// Gets the delta distance given two keys being pressed, or not being pressed.
-    fn d_distance(key1: bool, key2: bool, d: f64) -> f64 {
-        match (key1, key2) {
-            (true, false) => -d,
-            (false, true) => d,
-            _ => 0.0
+    /// Asks for the name of the player if they made it into the high
+    /// scores, and shows the game over menu otherwise.
+    fn game_over(&self, phi: &mut Phi) -> ViewAction {
+        let score = self.score.total();
+        if self.high_scores.qualifies(MODE, score) {
+            return match NameEntryView::new(phi, self.bgs.clone(), score) {
+                Ok(view) => ViewAction::Replace(Box::new(view)),
+                Err(e) => ErrorView::show(phi, e),
+            }
         }
+
+        match ::views::main_menu::MainMenuView::game_over(phi, self.bgs.clone(), score) {
+            Ok(menu) => ViewAction::Replace(Box::new(menu)),
+            Err(e) => ErrorView::show(phi, e),
+        }
+    }
+
+    /// How far the player moves during `elapsed` seconds. The speed scales
+    /// with how far the stick is pushed.
+    fn dx_dy(phi: &mut Phi, elapsed: f64) -> (f64, f64) {
+        let (x, y) = phi.events.movement();
+        (x * PLAYER_SPEED * elapsed, y * PLAYER_SPEED * elapsed)
     }
 
     /// Gets the movable region for the window size.
@@ -468,155 +977,465 @@ impl GameView {
         Rectangle { x: 0.0, y: 0.0, w: window.0 * 0.70, h: window.1 }
     }
 
-    /// Gets the next position of the player given the bounding window size.
-    fn next_player_rect(&self, dx: f64, dy: f64, window: (f64, f64)) -> Option<Rectangle> {
-        self.player.rect.moved_by(dx, dy).move_inside(Self::movable_region(window))
-    }
-
-}
-
-impl View for GameView {
-    fn render(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
-        // quitting quits!
-        if phi.events.now.quit {
-            return ViewAction::Quit;
-        }
-
-        // esc toggles game
-        if phi.events.now.key_escape == Some(true) {
-            return ViewAction::ChangeView(
-                Box::new(::views::main_menu::MainMenuView::with_backgrounds(phi, self.bgs.clone()))
-            );
-        }
-
-        // change bullet type
-        if phi.events.now.key_1 == Some(true) {
+    /// Switches to the cannon which the player picked.
+    fn select_weapon(&mut self, phi: &mut Phi) {
+        if phi.events.pressed(Action::SelectWeapon(1)) {
             self.player.cannon = CannonType::RectBullet;
         }
 
-        if phi.events.now.key_2 == Some(true) {
+        if phi.events.pressed(Action::SelectWeapon(2)) {
             self.player.cannon = CannonType::SineBullet {
                 amplitude: 10.0,
                 angular_vel: 15.0
             };
         }
 
-        if phi.events.now.key_3 == Some(true) {
+        if phi.events.pressed(Action::SelectWeapon(3)) {
             self.player.cannon = CannonType::DivergentBullet {
                 a: 100.0,
                 b: 1.2,
             };
         }
+    }
+
+    /// Brings the ship back a while after it was destroyed, and lets its
+    /// cannon cool down. Returns `false` once the last one is gone.
+    fn respawn(&mut self, dt: f64) -> bool {
+        if let Some(time) = self.respawn_in {
+            if time > dt {
+                self.respawn_in = Some(time - dt);
+            } else if self.lives == 0 {
+                return false;
+            } else {
+                self.respawn_in = None;
+                self.player.respawn(&mut self.world);
+            }
+        }
+        self.player.invulnerable = (self.player.invulnerable - dt).max(0.0);
+        self.player.cool_down(dt);
+        true
+    }
+
+    /// Moves the ship the way the player steers it, and picks the frame
+    /// which shows which way it goes.
+    fn steer(&mut self, phi: &mut Phi, dt: f64, window: (f64, f64)) {
+        if self.player_alive() {
+            let (dx, dy) = Self::dx_dy(phi, dt);
+
+            if let Some(transform) = self.world.transforms.get_mut(self.player.entity) {
+                transform.prev = transform.rect;
+                // the ship stays where it is while the window is too small for it
+                if let Some(rect) = transform.rect.moved_by(dx, dy).move_inside(Self::movable_region(window)) {
+                    transform.rect = rect;
+                }
+            }
+
+            self.player.current = ShipFrame::from_dx_dy(dx, dy);
+        }
+        if let Some(hitbox) = self.world.hitboxes.get_mut(self.player.entity) {
+            hitbox.frame = self.player.current as usize;
+        }
+    }
 
-        // Update all the current things
-        let (dx, dy) = Self::dx_dy(phi, elapsed);
+    /// Runs the boss system, and moves on to the next stage once the boss
+    /// is gone.
+    fn update_boss(&mut self, phi: &mut Phi, dt: f64) {
+        let mut blasts = vec![];
+        let gone = match self.boss {
+            Some(ref mut boss) => !boss.update(phi, &mut self.world, dt, &mut blasts),
+            None => false,
+        };
+        for center in blasts {
+            self.explosion_factory.at_center(&mut self.world, center);
+        }
 
-        // player position
-        self.player.rect = self.next_player_rect(dx, dy, phi.output_size()).unwrap();
+        if gone {
+            if let Some(boss) = self.boss.take() {
+                self.world.destroy(boss.entity);
+            }
+            self.wave += 1;
+            self.wave_left = WAVE_TIME;
+        }
+    }
 
-        // ship sprite
-        self.player.current = ShipFrame::from_dx_dy(dx, dy);
+    /// The collision system of the game: bullets of the player hurt what
+    /// they hit first, and whatever else touches the ship goes away with
+    /// it. Spent bullets are destroyed, but their cannon is known until
+    /// they are forgotten. Returns whether the ship was hit.
+    fn collide(&mut self, area: Rectangle) -> bool {
+        let can_be_hit = self.player_alive() && self.player.can_be_hit();
+        let mut player_hit = false;
+        let player = self.player.entity;
+
+        // bullets of the player stop at whatever they hit first, the rest
+        // of the contacts are with the ship
+        self.first_hits.clear();
+        let mut rammed = vec![];
+        for contact in self.world.collisions(area) {
+            let (bullet, other) = if self.shots.get(contact.a).is_some() {
+                (contact.a, contact.b)
+            } else if self.shots.get(contact.b).is_some() {
+                (contact.b, contact.a)
+            } else {
+                if can_be_hit {
+                    player_hit = true;
+                    rammed.push(if contact.a == player { contact.b } else { contact.a });
+                }
+                continue;
+            };
 
-        // bullets
-        let old_bullets = ::std::mem::replace(&mut self.bullets, vec![]);
-        self.bullets = old_bullets.into_iter()
-            .filter_map(|bullet| bullet.update(phi, elapsed))
-            .collect();
+            // bullets go through what cannot be destroyed
+            if self.world.health.get(other).is_some() {
+                hit_first(&mut self.first_hits, bullet, contact.time, Target::Entity(other));
+            }
+        }
 
-        // asteroid sprites
-        let old_asteroids = ::std::mem::replace(&mut self.asteroids, vec![]);
-        self.asteroids = old_asteroids.into_iter()
-            .filter_map(|asteroid| asteroid.update(elapsed))
-            .collect();
+        if let Some(ref boss) = self.boss {
+            for (bullet, _) in self.shots.iter() {
+                if let Some((time, _)) = boss.part_hit(&self.world, bullet) {
+                    hit_first(&mut self.first_hits, bullet, time, Target::Boss);
+                }
+            }
+        }
 
-        self.explosions = ::std::mem::replace(&mut self.explosions, vec![])
-            .into_iter()
-            .filter_map(|explosion| explosion.update(elapsed))
+        // unless what a bullet hit was already destroyed by another one
+        let hits: Vec<(Entity, Target)> = self.first_hits.iter()
+            .map(|(bullet, &(_, target))| (bullet, target))
             .collect();
+        for (bullet, target) in hits {
+            let spent = match target {
+                Target::Entity(other) => self.world.hurt(other, 1, bullet),
+                Target::Boss => {
+                    let hit = match self.boss {
+                        Some(ref mut boss) => boss.take_hit(&mut self.world, bullet),
+                        None => BossHit::Miss,
+                    };
+                    match hit {
+                        BossHit::Miss => false,
+                        BossHit::Armor | BossHit::Weak | BossHit::Destroyed => true,
+                        BossHit::PhaseChange => {
+                            let center = self.world.transforms.get(bullet).map(|transform| transform.rect.center());
+                            if let Some(center) = center {
+                                self.explosion_factory.at_center(&mut self.world, center);
+                            }
+                            true
+                        },
+                    }
+                },
+            };
 
-        let mut player_alive = true;
-        let mut transition_bullets: Vec<_> =
-            ::std::mem::replace(&mut self.bullets, vec![])
-            .into_iter()
-            .map(|bullet| MaybeAlive { alive: true, value: bullet })
-            .collect();
+            if spent {
+                self.world.destroy(bullet);
+            }
+        }
 
-        self.asteroids = ::std::mem::replace(&mut self.asteroids, vec![])
-            .into_iter()
-            .filter_map(|asteroid| {
-                // start alive
-                let mut asteroid_alive = true;
-
-                // check if we're hitting bullets
-                for bullet in &mut transition_bullets {
-                    if asteroid.rect().overlaps(bullet.value.rect()) {
-                        asteroid_alive = false;
-                        bullet.alive = false;
-                    }
-                }
+        // asteroids and enemies which ram the ship explode with it, its
+        // bullets just go away
+        for other in rammed {
+            let center = match self.world.transforms.get(other) {
+                Some(transform) => transform.rect.center(),
+                None => continue,
+            };
+            if self.world.points.get(other).is_some() {
+                self.explosion_factory.at_center(&mut self.world, center);
+            }
+            self.world.destroy(other);
+        }
 
-                if asteroid.rect().overlaps(self.player.rect) {
-                    asteroid_alive = false;
-                    player_alive = false;
+        if let Some(ref boss) = self.boss {
+            if let (Some(shape), Some(transform)) = (self.world.shape(player), self.world.transforms.get(player)) {
+                let at = transform.position();
+                if can_be_hit && (boss.overlaps(&self.world, shape, at) || boss.laser_hits(&self.world, shape, at)) {
+                    player_hit = true;
                 }
+            }
+        }
 
-                if asteroid_alive {
-                    Some(asteroid)
+        player_hit
+    }
+
+    /// The scoring system: whatever was destroyed explodes, and its points
+    /// go to the weapon which destroyed it.
+    fn score_deaths(&mut self) {
+        let deaths = ::std::mem::replace(&mut self.world.deaths, vec![]);
+        for death in deaths {
+            let weapon = self.shots.get(death.by).map_or(0, |cannon| cannon.weapon());
+            self.score.hit(death.points, weapon);
+            self.explosion_factory.at_center(&mut self.world, death.center);
+        }
+    }
+
+    /// Blows up the ship, which comes back after a while if the player has
+    /// any left.
+    fn lose_ship(&mut self) {
+        let center = self.player_rect().center();
+        self.explosion_factory.at_center(&mut self.world, center);
+        self.lives -= 1;
+        self.respawn_in = Some(RESPAWN_DELAY);
+    }
+
+    /// Fires the cannon of the ship when the player asks for it.
+    fn fire(&mut self, phi: &mut Phi) {
+        if !self.player_alive() || !phi.events.pressed(Action::Fire) {
+            return;
+        }
+
+        let bullets = self.player.fire(self.player_rect());
+        if !bullets.is_empty() {
+            for (rect, cannon) in bullets {
+                spawn_shot(&mut self.world, &mut self.shots, rect, cannon);
+            }
+            phi.audio.play(&self.fire_sound);
+        }
+    }
+
+    /// The ship shows which way it moves, and blinks while it cannot be
+    /// hit.
+    fn show_ship(&mut self) {
+        let visible = self.player_alive() && self.player.is_visible();
+        if let Some(look) = self.world.looks.get_mut(self.player.entity) {
+            look.visual = Visual::Sprite(self.player.sprites[self.player.current as usize].clone());
+            look.visible = visible;
+        }
+    }
+
+    /// The wave system: asteroids and enemies come more and more often, and
+    /// every stage ends with a boss, during which nothing else comes.
+    fn spawn(&mut self, phi: &mut Phi, dt: f64, window: (f64, f64)) {
+        if self.boss.is_none() {
+            self.wave_left -= dt;
+            if self.wave_left <= 0.0 {
+                if self.wave % STAGE_WAVES == 0 {
+                    self.boss = Some(Boss::new(&mut self.world, self.boss_sprite.clone(), window));
                 } else {
-                    self.explosions.push(self.explosion_factory.at_center(asteroid.rect().center()));
-                    None
+                    self.wave += 1;
+                    self.wave_left += WAVE_TIME;
                 }
+            }
+        }
 
-            })
-            .collect();
+        if self.boss.is_none() {
+            let chance = ASTEROID_CHANCE * (1.0 + WAVE_CHANCE_STEP * (self.wave - 1) as f64);
+            if phi.rng.chance(chance) {
+                self.asteroid_factory.random(phi, &mut self.world);
+            }
 
-        self.bullets = transition_bullets.into_iter()
-            .filter_map(MaybeAlive::as_option)
-            .collect();
+            let chance = ENEMY_CHANCE * (1.0 + WAVE_CHANCE_STEP * (self.wave - 1) as f64);
+            if phi.rng.chance(chance) {
+                self.enemy_factory.random(phi, &mut self.world);
+            }
+        }
+    }
 
-        if !player_alive {
-            println!("GO HOMEEEEE");
+    /// Shows what happened to the score, and how the game goes, in the hud.
+    fn update_hud(&mut self, phi: &mut Phi, dt: f64) -> Result<(), Error> {
+        self.score.update(dt);
+        for event in self.score.events(self.hud_scores) {
+            try!(self.hud.notify(phi, &event));
         }
 
-        if phi.events.now.key_space == Some(true) {
-            self.bullets.append(&mut self.player.spawn_bullets());
+        let status = Status {
+            lives: self.lives,
+            weapon: self.player.cannon.name(),
+            heat: self.player.heat,
+            overheated: self.player.overheated,
+            wave: self.wave,
+            boss: self.boss.as_ref()
+                .and_then(|boss| if boss.dying.is_none() { Some(boss.health_left()) } else { None }),
+        };
+        self.hud.update(phi, dt, status)
+    }
+}
+
+impl View for GameView {
+    fn update(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
+        // quitting quits!
+        if phi.events.now.quit {
+            return ViewAction::Quit;
+        }
+
+        // esc pauses the game, the menu pops back to it; so does switching
+        // to another window
+        if phi.events.pressed(Action::Pause) || phi.events.now.focus_lost {
+            return match ::views::main_menu::MainMenuView::paused(phi, self.bgs.clone()) {
+                Ok(menu) => ViewAction::Push(Box::new(menu)),
+                Err(e) => ErrorView::show(phi, e),
+            }
         }
 
-        if rand::<usize>() % 100 == 0 {
-            self.asteroids.push(self.asteroid_factory.random(phi));
+        self.select_weapon(phi);
+        if !self.respawn(elapsed) {
+            return self.game_over(phi);
         }
 
-        // Render all the things
+        let window = phi.output_size();
+        let area = Rectangle::with_size(window.0, window.1);
+        self.steer(phi, elapsed, window);
+        let target = if self.player_alive() { Some(self.player_rect().center()) } else { None };
+
+        // everything else moves, and the enemies fire
+        self.world.charge(target);
+        self.world.move_all(elapsed, area);
+        self.world.halt(elapsed);
+        self.world.follow_paths(elapsed);
+        self.world.animate(elapsed);
+        let explosions = self.world.lifetimes.len();
+        self.update_boss(phi, elapsed);
+        for (center, dir) in self.world.fire(elapsed, area, target) {
+            spawn_enemy_bullet(&mut self.world, center, dir);
+        }
 
+        if self.collide(area) {
+            self.lose_ship();
+        }
+        self.score_deaths();
+        self.forget_destroyed();
+        if self.world.lifetimes.len() > explosions {
+            phi.audio.play(&self.explosion_sound);
+        }
+
+        self.fire(phi);
+        self.show_ship();
+        self.spawn(phi, elapsed, window);
+
+        if let Err(e) = self.update_hud(phi, elapsed) {
+            return ErrorView::show(phi, e);
+        }
+        self.bgs.update(elapsed);
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, alpha: f64) {
         // clear
         phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
         phi.renderer.clear();
 
         // bgs
-        self.bgs.render(&mut phi.renderer, elapsed);
+        self.bgs.render(&mut *phi.renderer);
 
+        // everything in the game, each on its layer
+        self.world.render(phi, alpha);
         if DEBUG {
-            phi.renderer.set_draw_color(Color::RGB(200, 200, 50));
-            phi.renderer.fill_rect(self.player.rect.to_sdl().unwrap());
+            self.world.render_hitboxes(phi, alpha);
         }
 
-        // the ship
-        phi.renderer.copy_sprite(
-            &self.player.sprites[self.player.current as usize],
-            self.player.rect);
-
-        for bullet in &self.bullets {
-            bullet.render(phi);
+        if let Some(ref boss) = self.boss {
+            boss.render(phi, &self.world, alpha);
         }
 
-        for asteroid in &self.asteroids {
-            asteroid.render(phi);
-        }
+        self.hud.render(phi);
+    }
+
+    fn on_enter(&mut self, phi: &mut Phi) {
+        phi.audio.play_music(&self.music);
+    }
+
+    fn on_pause(&mut self, phi: &mut Phi) {
+        phi.audio.pause_music();
+    }
+
+    fn on_resume(&mut self, phi: &mut Phi) {
+        phi.audio.resume_music();
+    }
 
-        for explosion in &self.explosions {
-            explosion.render(phi);
+    fn on_exit(&mut self, phi: &mut Phi) {
+        phi.audio.stop_music();
+    }
+}
+
+/// Keeps `target` as what `bullet` hit first, if it hit it at `time`,
+/// before anything else.
+fn hit_first(hits: &mut Components<(f64, Target)>, bullet: Entity, time: f64, target: Target) {
+    if hits.get(bullet).map_or(true, |&(first, _)| time < first) {
+        hits.insert(bullet, (time, target));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::phi::backend::DrawCall;
+    use ::phi::input::{Bindings, InputEvent};
+    use ::phi::replay::Replay;
+    use ::phi::run_headless;
+    use ::phi::testing::headless;
+    use ::sdl2::keyboard::Keycode;
+    use ::sdl2::pixels::Color;
+    use ::views::shared::BackgroundSet;
+    use super::{GameView, PLAYER_START};
+
+    fn run(ticks: u32) -> Vec<DrawCall> {
+        headless(1, |phi| {
+            run_headless(phi, ticks, 60.0, |phi| {
+                let bgs = try!(BackgroundSet::new(phi));
+                Ok(Box::new(try!(GameView::new(phi, bgs))))
+            }).unwrap()
+        })
+    }
+
+    #[test]
+    fn draws_the_ship_where_it_starts() {
+        let calls = run(10);
+
+        assert_eq!(calls[0], DrawCall::Clear(Color::RGB(0, 0, 0)));
+        assert!(calls.iter().any(|call| match *call {
+            DrawCall::Copy { dest, .. } => (dest.x, dest.y) == PLAYER_START,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn draws_the_hud_over_the_ship() {
+        let calls = run(10);
+
+        let ship = calls.iter().position(|call| match *call {
+            DrawCall::Copy { dest, .. } => (dest.x, dest.y) == PLAYER_START,
+            _ => false,
+        }).unwrap();
+        let gauges: Vec<usize> = calls.iter().enumerate().filter_map(|(i, call)| match *call {
+            DrawCall::FillRect(color, _) if color == Color::RGB(60, 60, 60) => Some(i),
+            _ => None,
+        }).collect();
+
+        // only the heat gauge, as there is no boss yet
+        assert_eq!(gauges.len(), 1);
+        assert!(gauges[0] > ship);
+    }
+
+    #[test]
+    fn replays_play_out_the_same_way() {
+        // flies down and right while firing, with every weapon in turn
+        let mut replay = Replay::new(7, (800, 600), Bindings::default());
+        for tick in 0..900 {
+            let mut inputs = vec![];
+            match tick % 120 {
+                0 => inputs.push(InputEvent::KeyDown(Keycode::Right)),
+                40 => inputs.push(InputEvent::KeyUp(Keycode::Right)),
+                60 => inputs.push(InputEvent::KeyDown(Keycode::Down)),
+                100 => inputs.push(InputEvent::KeyUp(Keycode::Down)),
+                _ => {},
+            }
+            if tick % 300 == 0 {
+                inputs.push(InputEvent::KeyDown([Keycode::Num1, Keycode::Num2, Keycode::Num3][tick / 300]));
+            }
+            if tick % 10 == 0 {
+                inputs.push(InputEvent::KeyDown(Keycode::Space));
+            } else if tick % 10 == 1 {
+                inputs.push(InputEvent::KeyUp(Keycode::Space));
+            }
+            replay.push_tick(inputs);
         }
 
-        ViewAction::None
+        let play = |replay: Replay| headless(1, |phi| {
+            phi.play(replay);
+            run_headless(phi, 900, 60.0, |phi| {
+                let bgs = try!(BackgroundSet::new(phi));
+                Ok(Box::new(try!(GameView::new(phi, bgs))))
+            }).unwrap()
+        });
+
+        let first = play(replay.clone());
+        assert!(!first.is_empty());
+        assert_eq!(first, play(replay));
     }
 }
@@ -0,0 +1,477 @@
+use ::phi::{Error, Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::input::Action;
+use ::phi::storage;
+use ::rustc_serialize::json::Json;
+use ::views::error::ErrorView;
+use ::views::shared::BackgroundSet;
+use ::sdl2::mouse::Mouse;
+use ::sdl2::pixels::Color;
+use ::std::collections::BTreeMap;
+use ::std::fs::{self, File};
+use ::std::io::{ErrorKind, Read};
+use ::std::path::PathBuf;
+
+// Consts
+const FILE_NAME: &'static str = "highscores.json";
+/// The version of the format of the high scores file.
+const VERSION: u64 = 1;
+/// How many scores are kept for every mode.
+pub const TABLE_SIZE: usize = 10;
+pub const MAX_NAME_LEN: usize = 8;
+/// Given to the scores whose name was left blank.
+pub const DEFAULT_NAME: &'static str = "PLAYER";
+
+const FONT: &'static str = "assets/belligerent.ttf";
+const FONT_SIZE: i32 = 24;
+const TITLE_SIZE: i32 = 48;
+const ROW_H: f64 = 32.0;
+const BOX_W: f64 = 420.0;
+const TITLE_MARGIN: f64 = 30.0;
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub score: u64,
+}
+
+/// The best scores for every mode of the game, stored in the data directory
+/// of the player as a JSON file such as:
+///
+/// ```json
+/// {
+///     "version": 1,
+///     "tables": {
+///         "arcade": [ { "name": "ACE", "score": 12340 } ]
+///     }
+/// }
+/// ```
+///
+/// A damaged file never prevents the game from running: invalid entries are
+/// skipped, and a file which cannot be read at all is moved aside and
+/// replaced by an empty table. A file written by a newer version of the game
+/// is read as well as possible, but never overwritten.
+pub struct HighScores {
+    tables: BTreeMap<String, Vec<Entry>>,
+    path: PathBuf,
+    read_only: bool,
+}
+
+impl HighScores {
+    pub fn load() -> HighScores {
+        HighScores::load_from(storage::data_dir().join(FILE_NAME))
+    }
+
+    pub fn load_from(path: PathBuf) -> HighScores {
+        let mut scores = HighScores {
+            tables: BTreeMap::new(),
+            path: path,
+            read_only: false,
+        };
+
+        let mut contents = String::new();
+        match File::open(&scores.path).and_then(|mut file| file.read_to_string(&mut contents)) {
+            Ok(_) => {},
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return scores,
+            Err(e) => {
+                // the file may be fine, it is just not ours to overwrite
+                println!("Could not read {}: {}", scores.path.display(), e);
+                scores.read_only = true;
+                return scores;
+            },
+        }
+
+        if let Err(e) = scores.parse(&contents) {
+            println!("Ignoring the high scores in {}: {}", scores.path.display(), e);
+
+            // keep the damaged file around rather than overwriting it
+            let backup = scores.path.with_extension("json.bad");
+            if let Err(e) = fs::rename(&scores.path, &backup) {
+                println!("Could not move {} aside: {}", scores.path.display(), e);
+                scores.read_only = true;
+            }
+        }
+
+        scores
+    }
+
+    fn parse(&mut self, contents: &str) -> Result<(), String> {
+        let json = try!(Json::from_str(contents).map_err(|e| e.to_string()));
+
+        let version = try!(json.find("version").and_then(Json::as_u64)
+            .ok_or("`version` is missing".to_string()));
+        if version > VERSION {
+            println!("{} was written by a newer version of the game, it will not be updated",
+                     self.path.display());
+            self.read_only = true;
+        }
+
+        let tables = try!(json.find("tables").and_then(Json::as_object)
+            .ok_or("`tables` is missing".to_string()));
+
+        for (mode, entries) in tables {
+            let entries = match entries.as_array() {
+                Some(entries) => entries,
+                None => {
+                    println!("Skipping the high scores of `{}`: expected a list", mode);
+                    continue;
+                },
+            };
+
+            let mut table: Vec<Entry> = entries.iter().filter_map(|entry| {
+                let name = entry.find("name").and_then(Json::as_string);
+                let score = entry.find("score").and_then(Json::as_u64);
+                match (name, score) {
+                    (Some(name), Some(score)) => Some(Entry { name: clean_name(name), score: score }),
+                    _ => None,
+                }
+            }).collect();
+
+            if table.len() < entries.len() {
+                println!("Skipped {} invalid high scores of `{}`", entries.len() - table.len(), mode);
+            }
+
+            table.sort_by(|a, b| b.score.cmp(&a.score));
+            table.truncate(TABLE_SIZE);
+            self.tables.insert(mode.clone(), table);
+        }
+
+        Ok(())
+    }
+
+    /// The best scores of `mode`, highest first.
+    pub fn table(&self, mode: &str) -> &[Entry] {
+        self.tables.get(mode).map(|table| &table[..]).unwrap_or(&[])
+    }
+
+    /// Whether `score` would make it into the table of `mode`.
+    pub fn qualifies(&self, mode: &str, score: u64) -> bool {
+        let table = self.table(mode);
+        score > 0 && (table.len() < TABLE_SIZE || table.iter().any(|entry| score > entry.score))
+    }
+
+    /// Adds a score to the table of `mode`, returning its rank, starting
+    /// from 0, unless it did not make it. Ties rank below older scores.
+    pub fn insert(&mut self, mode: &str, name: &str, score: u64) -> Option<usize> {
+        if !self.qualifies(mode, score) {
+            return None;
+        }
+
+        let table = self.tables.entry(mode.to_string()).or_insert(vec![]);
+        let rank = table.iter().position(|entry| score > entry.score).unwrap_or(table.len());
+        table.insert(rank, Entry { name: clean_name(name), score: score });
+        table.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err(format!("Not overwriting {}", self.path.display()));
+        }
+
+        let mut tables = BTreeMap::new();
+        for (mode, table) in &self.tables {
+            let entries = table.iter().map(|entry| {
+                let mut object = BTreeMap::new();
+                object.insert("name".to_string(), Json::String(entry.name.clone()));
+                object.insert("score".to_string(), Json::U64(entry.score));
+                Json::Object(object)
+            }).collect();
+            tables.insert(mode.clone(), Json::Array(entries));
+        }
+
+        let mut json = BTreeMap::new();
+        json.insert("version".to_string(), Json::U64(VERSION));
+        json.insert("tables".to_string(), Json::Object(tables));
+
+        storage::write(&self.path, &Json::Object(json).pretty().to_string())
+    }
+}
+
+/// `name`, trimmed and cut to `MAX_NAME_LEN`, or `DEFAULT_NAME` if that
+/// leaves nothing.
+fn clean_name(name: &str) -> String {
+    let name: String = name.trim().chars().take(MAX_NAME_LEN).collect();
+    if name.is_empty() {
+        DEFAULT_NAME.to_string()
+    } else {
+        name
+    }
+}
+
+
+struct Row {
+    rank: Sprite,
+    name: Sprite,
+    score: Sprite,
+}
+
+/// Shows the table of a mode. After a game, the new score is highlighted
+/// and leaving goes on to the game over menu.
+pub struct HighScoresView {
+    title: Sprite,
+    rows: Vec<Row>,
+    /// Shown instead of the rows when there are none.
+    empty: Sprite,
+    bgs: BackgroundSet,
+    /// The score of the game which just ended, if any.
+    game_over: Option<u64>,
+    /// Shown under the rows when that score could not be saved.
+    notice: Option<Sprite>,
+}
+
+impl HighScoresView {
+    pub fn new(phi: &mut Phi, bgs: BackgroundSet, mode: &str) -> Result<HighScoresView, Error> {
+        HighScoresView::build(phi, bgs, mode, None, None)
+    }
+
+    /// The table right after a game scored `score`, which ranked `rank`, or
+    /// which could not be saved if `rank` is `None`.
+    pub fn after_game(phi: &mut Phi, bgs: BackgroundSet, mode: &str, rank: Option<usize>, score: u64)
+        -> Result<HighScoresView, Error> {
+        let mut view = try!(HighScoresView::build(phi, bgs, mode, rank, Some(score)));
+        if rank.is_none() {
+            view.notice = Some(try!(phi.ttf_str_sprite("Your score could not be saved", FONT, FONT_SIZE,
+                Color::RGB(255, 90, 60))));
+        }
+        Ok(view)
+    }
+
+    fn build(phi: &mut Phi, bgs: BackgroundSet, mode: &str, highlight: Option<usize>, game_over: Option<u64>)
+        -> Result<HighScoresView, Error> {
+        let mut rows = vec![];
+        for (i, entry) in HighScores::load().table(mode).iter().enumerate() {
+            let color = if highlight == Some(i) {
+                Color::RGB(255, 220, 60)
+            } else {
+                Color::RGB(220, 220, 220)
+            };
+
+            rows.push(Row {
+                rank: try!(phi.ttf_str_sprite(&format!("{}.", i + 1), FONT, FONT_SIZE, color)),
+                name: try!(phi.ttf_str_sprite(&entry.name, FONT, FONT_SIZE, color)),
+                score: try!(phi.ttf_str_sprite(&entry.score.to_string(), FONT, FONT_SIZE, color)),
+            });
+        }
+
+        Ok(HighScoresView {
+            title: try!(phi.ttf_str_sprite("High Scores", FONT, TITLE_SIZE, Color::RGB(255, 255, 255))),
+            rows: rows,
+            empty: try!(phi.ttf_str_sprite("No scores yet", FONT, FONT_SIZE, Color::RGB(220, 220, 220))),
+            bgs: bgs,
+            game_over: game_over,
+            notice: None,
+        })
+    }
+}
+
+impl View for HighScoresView {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit;
+        }
+
+        self.bgs.update(dt);
+
+        if phi.events.pressed(Action::Confirm) || phi.events.pressed(Action::Pause) ||
+           phi.events.mouse_pressed(Mouse::Left) {
+            return match self.game_over {
+                Some(score) => match ::views::main_menu::MainMenuView::game_over(phi, self.bgs.clone(), score) {
+                    Ok(menu) => ViewAction::Replace(Box::new(menu)),
+                    Err(e) => ErrorView::show(phi, e),
+                },
+                None => ViewAction::Pop,
+            }
+        }
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _alpha: f64) {
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        self.bgs.render(&mut *phi.renderer);
+
+        let (win_w, win_h) = phi.output_size();
+        let box_h = ::std::cmp::max(self.rows.len(), 1) as f64 * ROW_H;
+        let box_x = (win_w - BOX_W) / 2.0;
+        let box_y = (win_h - box_h) / 2.0;
+
+        let (w, h) = self.title.size();
+        phi.renderer.copy_sprite(&self.title, Rectangle {
+            x: (win_w - w) / 2.0,
+            y: box_y - TITLE_MARGIN - h,
+            w: w,
+            h: h,
+        });
+
+        if self.rows.is_empty() {
+            let (w, h) = self.empty.size();
+            phi.renderer.copy_sprite(&self.empty, Rectangle {
+                x: (win_w - w) / 2.0,
+                y: box_y + (ROW_H - h) / 2.0,
+                w: w,
+                h: h,
+            });
+        }
+
+        for (i, row) in self.rows.iter().enumerate() {
+            let y = box_y + ROW_H * i as f64;
+
+            // the rank and the score are right aligned, the name left aligned
+            let (w, h) = row.rank.size();
+            phi.renderer.copy_sprite(&row.rank, Rectangle { x: box_x + 40.0 - w, y: y + (ROW_H - h) / 2.0, w: w, h: h });
+
+            let (w, h) = row.name.size();
+            phi.renderer.copy_sprite(&row.name, Rectangle { x: box_x + 60.0, y: y + (ROW_H - h) / 2.0, w: w, h: h });
+
+            let (w, h) = row.score.size();
+            phi.renderer.copy_sprite(&row.score, Rectangle { x: box_x + BOX_W - w, y: y + (ROW_H - h) / 2.0, w: w, h: h });
+        }
+
+        if let Some(ref notice) = self.notice {
+            let (w, h) = notice.size();
+            phi.renderer.copy_sprite(notice, Rectangle {
+                x: (win_w - w) / 2.0,
+                y: box_y + box_h + TITLE_MARGIN,
+                w: w,
+                h: h,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_NAME, Entry, HighScores, MAX_NAME_LEN, TABLE_SIZE, VERSION, clean_name};
+    use ::std::fs::{self, File};
+    use ::std::io::{Read, Write};
+    use ::std::path::PathBuf;
+
+    /// Where a test keeps its high scores, in a directory of its own which
+    /// starts out empty.
+    fn scratch(test: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!("arcade-rs-{}-{}", ::std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("highscores.json")
+    }
+
+    fn load(test: &str, contents: &str) -> (HighScores, PathBuf) {
+        let path = scratch(test);
+        File::create(&path).and_then(|mut file| file.write_all(contents.as_bytes())).unwrap();
+        (HighScores::load_from(path.clone()), path)
+    }
+
+    fn entry(name: &str, score: u64) -> Entry {
+        Entry { name: name.to_string(), score: score }
+    }
+
+    #[test]
+    fn loads_what_was_saved() {
+        let path = scratch("saved");
+        let mut scores = HighScores::load_from(path.clone());
+        assert_eq!(scores.table("arcade"), &[]);
+        scores.insert("arcade", "ACE", 300);
+        scores.insert("arcade", "BOB", 500);
+        scores.save().unwrap();
+
+        let scores = HighScores::load_from(path);
+        assert_eq!(scores.table("arcade"), &[entry("BOB", 500), entry("ACE", 300)]);
+    }
+
+    #[test]
+    fn moves_invalid_files_aside() {
+        for (i, contents) in ["{ not json", r#"{ "tables": {} }"#, r#"{ "version": 1 }"#].iter().enumerate() {
+            let (scores, path) = load(&format!("invalid-{}", i), contents);
+            assert_eq!(scores.table("arcade"), &[]);
+            assert!(!path.exists());
+
+            let mut backup = String::new();
+            File::open(path.with_extension("json.bad")).unwrap().read_to_string(&mut backup).unwrap();
+            assert_eq!(&backup, contents);
+
+            // and starts over
+            scores.save().unwrap();
+            assert!(path.exists());
+        }
+    }
+
+    #[test]
+    fn skips_invalid_tables_and_entries() {
+        let (scores, _) = load("entries", r#"{
+            "version": 1,
+            "tables": {
+                "arcade": [
+                    { "name": "ACE", "score": 100 },
+                    { "score": 200 },
+                    { "name": "BOB" },
+                    { "name": "CAT", "score": "many" },
+                    { "name": "DAN", "score": 300 }
+                ],
+                "survival": { "name": "EVE", "score": 100 }
+            }
+        }"#);
+
+        assert_eq!(scores.table("arcade"), &[entry("DAN", 300), entry("ACE", 100)]);
+        assert_eq!(scores.table("survival"), &[]);
+    }
+
+    #[test]
+    fn never_overwrites_newer_files() {
+        let contents = format!(r#"{{ "version": {}, "tables": {{ "arcade": [ {{ "name": "ACE", "score": 100 }} ] }} }}"#,
+                               VERSION + 1);
+        let (mut scores, path) = load("newer", &contents);
+
+        assert_eq!(scores.table("arcade"), &[entry("ACE", 100)]);
+        assert!(scores.read_only);
+        scores.insert("arcade", "BOB", 200);
+        assert!(scores.save().is_err());
+
+        let mut kept = String::new();
+        File::open(path).unwrap().read_to_string(&mut kept).unwrap();
+        assert_eq!(kept, contents);
+    }
+
+    #[test]
+    fn ranks_new_scores() {
+        let mut scores = HighScores::load_from(scratch("ranks"));
+        assert_eq!(scores.insert("arcade", "ACE", 100), Some(0));
+        assert_eq!(scores.insert("arcade", "BOB", 300), Some(0));
+        assert_eq!(scores.insert("arcade", "CAT", 200), Some(1));
+
+        // ties rank below the older scores
+        assert_eq!(scores.insert("arcade", "DAN", 200), Some(2));
+        assert_eq!(scores.table("arcade")[1], entry("CAT", 200));
+
+        // nothing is ever worth a place
+        assert!(!scores.qualifies("arcade", 0));
+        assert_eq!(scores.insert("arcade", "EVE", 0), None);
+    }
+
+    #[test]
+    fn keeps_the_best_scores_only() {
+        let mut scores = HighScores::load_from(scratch("best"));
+        for score in 1..TABLE_SIZE as u64 + 1 {
+            scores.insert("arcade", "ACE", score * 10);
+        }
+        assert!(!scores.qualifies("arcade", 10));
+        assert_eq!(scores.insert("arcade", "BOB", 5), None);
+
+        assert_eq!(scores.insert("arcade", "BOB", 15), Some(TABLE_SIZE - 1));
+        let table = scores.table("arcade");
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(table[TABLE_SIZE - 1], entry("BOB", 15));
+    }
+
+    #[test]
+    fn cleans_names() {
+        assert_eq!(clean_name("  ACE "), "ACE");
+        assert_eq!(clean_name("ABCDEFGHIJ").len(), MAX_NAME_LEN);
+        assert_eq!(clean_name("   "), DEFAULT_NAME);
+        assert_eq!(clean_name(""), DEFAULT_NAME);
+    }
+}
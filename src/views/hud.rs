@@ -0,0 +1,222 @@
+use ::phi::{Error, Phi};
+use ::phi::data::Rectangle;
+use ::phi::gfx::{Sprite, CopySprite};
+use ::views::score::ScoreEvent;
+use ::sdl2::pixels::Color;
+
+// Consts
+const FONT: &'static str = "assets/belligerent.ttf";
+const FONT_SIZE: i32 = 24;
+const MARGIN: f64 = 10.0;
+const LIFE_ICON_SCALE: f64 = 0.5;
+const LIFE_ICON_SPACING: f64 = 5.0;
+const HEAT_W: f64 = 120.0;
+const HEAT_H: f64 = 8.0;
+/// The width of the health bar of a boss, as a part of the window's.
+const BOSS_BAR_W: f64 = 0.5;
+const BOSS_BAR_H: f64 = 10.0;
+/// How long the points of the latest hit stay on screen.
+const GAIN_TIME: f64 = 1.0;
+
+
+/// A line of text which is only rendered again when it changes, or when
+/// its font was reloaded.
+struct Label {
+    text: String,
+    color: Color,
+    /// `None` while the text is empty, which cannot be rendered.
+    sprite: Option<Sprite>,
+    /// The `Phi::font_generation` the sprite was rendered with.
+    generation: u32,
+}
+
+impl Label {
+    fn new(color: Color) -> Label {
+        Label {
+            text: String::new(),
+            color: color,
+            sprite: None,
+            generation: 0,
+        }
+    }
+
+    fn set(&mut self, phi: &mut Phi, text: &str) -> Result<(), Error> {
+        if text != self.text || self.generation != phi.font_generation() {
+            self.sprite = if text.is_empty() {
+                None
+            } else {
+                Some(try!(phi.ttf_str_sprite(text, FONT, FONT_SIZE, self.color)))
+            };
+            self.text = text.to_string();
+            self.generation = phi.font_generation();
+        }
+        Ok(())
+    }
+
+    /// Renders the same text again if the font was reloaded.
+    fn refresh(&mut self, phi: &mut Phi) -> Result<(), Error> {
+        let text = self.text.clone();
+        self.set(phi, &text)
+    }
+
+    fn size(&self) -> (f64, f64) {
+        self.sprite.as_ref().map_or((0.0, 0.0), Sprite::size)
+    }
+
+    fn render(&self, phi: &mut Phi, x: f64, y: f64) {
+        if let Some(ref sprite) = self.sprite {
+            let (w, h) = sprite.size();
+            phi.renderer.copy_sprite(sprite, Rectangle { x: x, y: y, w: w, h: h });
+        }
+    }
+}
+
+
+/// What the HUD shows besides the score, as of the current tick.
+pub struct Status {
+    pub lives: u32,
+    pub weapon: &'static str,
+    /// How hot the weapon is, between 0 and 1.
+    pub heat: f64,
+    /// Whether the weapon got too hot and cannot fire until it cools down.
+    pub overheated: bool,
+    pub wave: u32,
+    /// How much health the boss has left, between 0 and 1, while there
+    /// is one.
+    pub boss: Option<f64>,
+}
+
+/// Draws the state of the game on top of it: the lives and the weapon in
+/// the top left corner, the wave at the top, and the score in the top right
+/// corner. The score follows `ScoreEvent`s, everything else is read from a
+/// `Status` every tick.
+pub struct Hud {
+    life_icon: Sprite,
+    lives: u32,
+    weapon: Label,
+    heat: f64,
+    overheated: bool,
+    wave: Label,
+    boss: Option<f64>,
+    score: Label,
+    multiplier: Label,
+    gain: Label,
+    /// How long the latest gain is still shown.
+    gain_left: f64,
+}
+
+impl Hud {
+    /// `life_icon` is drawn, scaled down, once for every life left.
+    pub fn new(phi: &mut Phi, life_icon: Sprite) -> Result<Hud, Error> {
+        let white = Color::RGB(255, 255, 255);
+        let mut hud = Hud {
+            life_icon: life_icon,
+            lives: 0,
+            weapon: Label::new(Color::RGB(220, 220, 220)),
+            heat: 0.0,
+            overheated: false,
+            wave: Label::new(white),
+            boss: None,
+            score: Label::new(white),
+            multiplier: Label::new(Color::RGB(255, 220, 60)),
+            gain: Label::new(white),
+            gain_left: 0.0,
+        };
+        try!(hud.score.set(phi, "0"));
+        Ok(hud)
+    }
+
+    pub fn notify(&mut self, phi: &mut Phi, event: &ScoreEvent) -> Result<(), Error> {
+        match *event {
+            ScoreEvent::Scored { points, total } => {
+                try!(self.score.set(phi, &total.to_string()));
+                try!(self.gain.set(phi, &format!("+{}", points)));
+                self.gain_left = GAIN_TIME;
+            },
+            ScoreEvent::Multiplier(1) => try!(self.multiplier.set(phi, "")),
+            ScoreEvent::Multiplier(multiplier) => {
+                try!(self.multiplier.set(phi, &format!("x{}", multiplier)));
+            },
+        }
+        Ok(())
+    }
+
+    pub fn update(&mut self, phi: &mut Phi, dt: f64, status: Status) -> Result<(), Error> {
+        self.lives = status.lives;
+        self.heat = status.heat.max(0.0).min(1.0);
+        self.overheated = status.overheated;
+        self.boss = status.boss;
+        try!(self.weapon.set(phi, status.weapon));
+        try!(self.wave.set(phi, &format!("Wave {}", status.wave)));
+
+        self.gain_left -= dt;
+        if self.gain_left <= 0.0 {
+            try!(self.gain.set(phi, ""));
+        }
+
+        // the score only changes on events, but the font may change anytime
+        try!(self.score.refresh(phi));
+        try!(self.multiplier.refresh(phi));
+        try!(self.gain.refresh(phi));
+        Ok(())
+    }
+
+    pub fn render(&self, phi: &mut Phi) {
+        let (win_w, _) = phi.output_size();
+
+        // lives
+        let (icon_w, icon_h) = self.life_icon.size();
+        let (icon_w, icon_h) = (icon_w * LIFE_ICON_SCALE, icon_h * LIFE_ICON_SCALE);
+        for i in 0..self.lives {
+            phi.renderer.copy_sprite(&self.life_icon, Rectangle {
+                x: MARGIN + (icon_w + LIFE_ICON_SPACING) * i as f64,
+                y: MARGIN,
+                w: icon_w,
+                h: icon_h,
+            });
+        }
+
+        // weapon, and how hot it is
+        let weapon_y = MARGIN + icon_h + LIFE_ICON_SPACING;
+        self.weapon.render(phi, MARGIN, weapon_y);
+
+        let heat_y = weapon_y + self.weapon.size().1 + LIFE_ICON_SPACING;
+        phi.renderer.set_draw_color(Color::RGB(60, 60, 60));
+        phi.renderer.fill_rect(Rectangle { x: MARGIN, y: heat_y, w: HEAT_W, h: HEAT_H });
+        phi.renderer.set_draw_color(if self.overheated {
+            Color::RGB(230, 40, 40)
+        } else {
+            Color::RGB((80.0 + 150.0 * self.heat) as u8, (200.0 - 120.0 * self.heat) as u8, 40)
+        });
+        if self.heat > 0.0 {
+            phi.renderer.fill_rect(Rectangle { x: MARGIN, y: heat_y, w: HEAT_W * self.heat, h: HEAT_H });
+        }
+
+        // wave, and the health of the boss below it
+        let (w, h) = self.wave.size();
+        self.wave.render(phi, (win_w - w) / 2.0, MARGIN);
+
+        if let Some(health) = self.boss {
+            let bar = Rectangle {
+                x: win_w * (1.0 - BOSS_BAR_W) / 2.0,
+                y: MARGIN + h + LIFE_ICON_SPACING,
+                w: win_w * BOSS_BAR_W,
+                h: BOSS_BAR_H,
+            };
+            phi.renderer.set_draw_color(Color::RGB(60, 60, 60));
+            phi.renderer.fill_rect(bar);
+            if health > 0.0 {
+                phi.renderer.set_draw_color(Color::RGB(220, 40, 60));
+                phi.renderer.fill_rect(Rectangle { w: bar.w * health.min(1.0), .. bar });
+            }
+        }
+
+        // score, right aligned
+        let mut y = MARGIN;
+        for label in &[&self.score, &self.multiplier, &self.gain] {
+            let (w, h) = label.size();
+            label.render(phi, win_w - w - MARGIN, y);
+            y += h;
+        }
+    }
+}
@@ -1,11 +1,20 @@
-use ::phi::{Phi, View, ViewAction};
+use ::phi::{Error, Phi, View, ViewAction};
+use ::phi::audio::Sound;
 use ::phi::data::{Rectangle};
 use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::input::Action as InputAction;
+use ::views::error::ErrorView;
 use ::views::shared::BackgroundSet;
+use ::sdl2::mouse::Mouse;
 use ::sdl2::pixels::Color;
 
 // Consts
 const FONT: &'static str = "assets/belligerent.ttf";
+const LABEL_H: f64 = 50.0;
+const BOX_W: f64 = 360.0;
+const TITLE_SIZE: i32 = 48;
+const TITLE_MARGIN: f64 = 30.0;
+const SELECT_SOUND: &'static str = "assets/select.wav";
 
 // Types
 
@@ -17,20 +26,20 @@ struct Action {
 }
 
 impl Action {
-    fn new(phi: &mut Phi, label: &'static str, func: BoxAction) -> Action {
-        Action {
+    fn new(phi: &mut Phi, label: &'static str, func: BoxAction) -> Result<Action, Error> {
+        Ok(Action {
             func: func,
-            idle_sprite: Action::make_idle_sprite(phi, label),
-            hover_sprite: Action::make_hover_sprite(phi, label),
-        }
+            idle_sprite: try!(Action::make_idle_sprite(phi, label)),
+            hover_sprite: try!(Action::make_hover_sprite(phi, label)),
+        })
     }
 
-    fn make_idle_sprite(phi: &mut Phi, label: &'static str) -> Sprite {
-        phi.ttf_str_sprite(label, FONT, 32, Color::RGB(220, 220, 220)).unwrap()
+    fn make_idle_sprite(phi: &mut Phi, label: &'static str) -> Result<Sprite, Error> {
+        phi.ttf_str_sprite(label, FONT, 32, Color::RGB(220, 220, 220))
     }
 
-    fn make_hover_sprite(phi: &mut Phi, label: &'static str) -> Sprite {
-        phi.ttf_str_sprite(label, FONT, 38, Color::RGB(255, 255, 255)).unwrap()
+    fn make_hover_sprite(phi: &mut Phi, label: &'static str) -> Result<Sprite, Error> {
+        phi.ttf_str_sprite(label, FONT, 38, Color::RGB(255, 255, 255))
     }
 
 }
@@ -39,74 +48,210 @@ pub struct MainMenuView {
     actions: Vec<Action>,
     selected: i8,
     bgs: BackgroundSet,
+    /// Whether this menu was pushed on top of a running game.
+    paused: bool,
+    select_sound: Sound,
+    /// Shown above the actions, if any.
+    title: Option<Sprite>,
 }
 
 impl MainMenuView {
-    pub fn new(phi: &mut Phi) -> MainMenuView {
-        let bg = BackgroundSet::new(&mut phi.renderer);
+    pub fn new(phi: &mut Phi) -> Result<MainMenuView, Error> {
+        let bg = try!(BackgroundSet::new(phi));
         MainMenuView::with_backgrounds(phi, bg)
     }
 
-    pub fn with_backgrounds(phi: &mut Phi, bgs: BackgroundSet) -> MainMenuView {
-        MainMenuView {
-            actions: vec![
-                Action::new(phi, "New Game", Box::new(|phi, bgs| {
-                    ViewAction::ChangeView(Box::new(::views::game::GameView::new(phi, bgs)))
-                })),
-                Action::new(phi, "Quit", Box::new(|_, _| {
-                    ViewAction::Quit
-                })),
-            ],
+    pub fn with_backgrounds(phi: &mut Phi, bgs: BackgroundSet) -> Result<MainMenuView, Error> {
+        let actions = try!(MainMenuView::default_actions(phi));
+        MainMenuView::build(phi, bgs, actions, false, None)
+    }
+
+    /// The menu shown over a paused game, which stays frozen below it.
+    pub fn paused(phi: &mut Phi, bgs: BackgroundSet) -> Result<MainMenuView, Error> {
+        let actions = vec![
+            try!(Action::new(phi, "Resume", Box::new(|_, _| {
+                ViewAction::Pop
+            }))),
+            try!(Action::new(phi, "Restart", Box::new(|phi, bgs| {
+                match ::views::game::GameView::new(phi, bgs) {
+                    Ok(game) => ViewAction::Reset(Box::new(game)),
+                    Err(e) => ErrorView::show(phi, e),
+                }
+            }))),
+            try!(Action::new(phi, "Settings", Box::new(|phi, bgs| {
+                match ::views::controls::ControlsView::new(phi, bgs) {
+                    Ok(controls) => ViewAction::Push(Box::new(controls)),
+                    Err(e) => ErrorView::show(phi, e),
+                }
+            }))),
+            try!(Action::new(phi, "Quit to Menu", Box::new(|phi, bgs| {
+                match MainMenuView::with_backgrounds(phi, bgs) {
+                    Ok(menu) => ViewAction::Reset(Box::new(menu)),
+                    Err(e) => ErrorView::show(phi, e),
+                }
+            }))),
+        ];
+
+        let title = try!(phi.ttf_str_sprite("Paused", FONT, TITLE_SIZE, Color::RGB(255, 255, 255)));
+        MainMenuView::build(phi, bgs, actions, true, Some(title))
+    }
+
+    /// The menu shown once the player has lost their last ship, having
+    /// scored `score` points.
+    pub fn game_over(phi: &mut Phi, bgs: BackgroundSet, score: u64) -> Result<MainMenuView, Error> {
+        let actions = vec![
+            try!(Action::new(phi, "Retry", Box::new(|phi, bgs| {
+                match ::views::game::GameView::new(phi, bgs) {
+                    Ok(game) => ViewAction::Reset(Box::new(game)),
+                    Err(e) => ErrorView::show(phi, e),
+                }
+            }))),
+            try!(Action::new(phi, "Main Menu", Box::new(|phi, bgs| {
+                match MainMenuView::with_backgrounds(phi, bgs) {
+                    Ok(menu) => ViewAction::Reset(Box::new(menu)),
+                    Err(e) => ErrorView::show(phi, e),
+                }
+            }))),
+            try!(Action::new(phi, "Quit", Box::new(|_, _| {
+                ViewAction::Quit
+            }))),
+        ];
+
+        let title = format!("Game Over - {} points", score);
+        let title = try!(phi.ttf_str_sprite(&title, FONT, TITLE_SIZE, Color::RGB(255, 255, 255)));
+        MainMenuView::build(phi, bgs, actions, false, Some(title))
+    }
+
+    fn build(phi: &mut Phi, bgs: BackgroundSet, actions: Vec<Action>, paused: bool, title: Option<Sprite>)
+        -> Result<MainMenuView, Error> {
+        Ok(MainMenuView {
+            actions: actions,
             selected: 0,
             bgs: bgs,
+            paused: paused,
+            select_sound: try!(phi.audio.load(SELECT_SOUND)),
+            title: title,
+        })
+    }
+
+    fn default_actions(phi: &mut Phi) -> Result<Vec<Action>, Error> {
+        Ok(vec![
+            try!(Action::new(phi, "New Game", Box::new(|phi, bgs| {
+                match ::views::game::GameView::new(phi, bgs) {
+                    Ok(game) => ViewAction::Reset(Box::new(game)),
+                    Err(e) => ErrorView::show(phi, e),
+                }
+            }))),
+            try!(Action::new(phi, "High Scores", Box::new(|phi, bgs| {
+                match ::views::high_scores::HighScoresView::new(phi, bgs, ::views::game::MODE) {
+                    Ok(scores) => ViewAction::Push(Box::new(scores)),
+                    Err(e) => ErrorView::show(phi, e),
+                }
+            }))),
+            try!(Action::new(phi, "Controls", Box::new(|phi, bgs| {
+                match ::views::controls::ControlsView::new(phi, bgs) {
+                    Ok(controls) => ViewAction::Push(Box::new(controls)),
+                    Err(e) => ErrorView::show(phi, e),
+                }
+            }))),
+            try!(Action::new(phi, "Quit", Box::new(|_, _| {
+                ViewAction::Quit
+            }))),
+        ])
+    }
+
+    /// The region of the window in which the `i`th action is shown.
+    fn action_rect(&self, window: (f64, f64), i: usize) -> Rectangle {
+        let box_h = self.actions.len() as f64 * LABEL_H;
+        Rectangle {
+            x: (window.0 - BOX_W) / 2.0,
+            y: (window.1 - box_h) / 2.0 + LABEL_H * i as f64,
+            w: BOX_W,
+            h: LABEL_H,
         }
+    }
 
+    /// The index of the action under `point`, if any.
+    fn action_at(&self, window: (f64, f64), point: (f64, f64)) -> Option<usize> {
+        (0..self.actions.len()).find(|&i| self.action_rect(window, i).contains_point(point))
     }
 }
 
 impl View for MainMenuView {
-    fn render(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
 
         if phi.events.now.quit {
             return ViewAction::Quit;
         }
 
-        if phi.events.now.key_escape == Some(true) {
-            return ViewAction::ChangeView(Box::new(
-                ::views::game::GameView::new(phi, self.bgs.clone())
-            ))
+        // only the pause menu can be left this way, games are started
+        // from the actions
+        if self.paused && phi.events.pressed(InputAction::Pause) {
+            return ViewAction::Pop;
         }
 
-        if phi.events.now.key_space == Some(true) ||
-            phi.events.now.key_return == Some(true) {
+        if phi.events.pressed(InputAction::Confirm) {
             return (self.actions[self.selected as usize].func)(phi, self.bgs.clone())
         }
 
-        if phi.events.now.key_up == Some(true) {
+        let selected = self.selected;
+
+        // the mouse selects what it hovers, and clicking triggers it
+        let hovered = self.action_at(phi.output_size(), phi.events.mouse);
+        if let Some(i) = hovered {
+            if phi.events.now.mouse_moved || phi.events.mouse_pressed(Mouse::Left) {
+                self.selected = i as i8;
+            }
+            if phi.events.mouse_pressed(Mouse::Left) {
+                return (self.actions[i].func)(phi, self.bgs.clone())
+            }
+        }
+
+        if phi.events.pressed(InputAction::MoveUp) {
             self.selected -= 1;
             if self.selected < 0 {
                 self.selected = self.actions.len() as i8 - 1;
             }
         }
 
-        if phi.events.now.key_down == Some(true) {
+        if phi.events.pressed(InputAction::MoveDown) {
             self.selected += 1;
             if self.selected >= self.actions.len() as i8 {
                 self.selected = 0;
             }
         }
 
-        // clear the screen
-        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
-        phi.renderer.clear();
+        if self.selected != selected {
+            phi.audio.play(&self.select_sound);
+        }
+
+        // the game below is frozen, and so are its backgrounds
+        if !self.paused {
+            self.bgs.update(dt);
+        }
 
-        // bgs
-        self.bgs.render(&mut phi.renderer, elapsed);
+        ViewAction::None
+    }
 
+    fn render(&mut self, phi: &mut Phi, _alpha: f64) {
         let (win_w, win_h) = phi.output_size();
-        let label_h = 50.0;
+
+        if self.paused {
+            // dim the game
+            phi.renderer.set_draw_color(Color::RGBA(0, 0, 0, 160));
+            phi.renderer.fill_rect(Rectangle::with_size(win_w, win_h));
+        } else {
+            // clear the screen
+            phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+            phi.renderer.clear();
+
+            // bgs
+            self.bgs.render(&mut *phi.renderer);
+        }
+
+        let label_h = LABEL_H;
         let border_width = 3.0;
-        let box_w = 360.0;
+        let box_w = BOX_W;
         let box_h = self.actions.len() as f64 * label_h;
         let margin_h = 10.0;
 
@@ -116,7 +261,7 @@ impl View for MainMenuView {
             h: box_h + border_width * 2.0 + margin_h * 2.0,
             x: (win_w - box_w) / 2.0 - border_width,
             y: (win_h - box_h) / 2.0 - margin_h - border_width,
-        }.to_sdl().unwrap());
+        });
 
         phi.renderer.set_draw_color(Color::RGB(140, 30, 140));
         phi.renderer.fill_rect(Rectangle {
@@ -124,7 +269,17 @@ impl View for MainMenuView {
             h: box_h + margin_h * 2.0,
             x: (win_w - box_w) / 2.0,
             y: (win_h - box_h) / 2.0 - margin_h,
-        }.to_sdl().unwrap());
+        });
+
+        if let Some(ref title) = self.title {
+            let (w, h) = title.size();
+            phi.renderer.copy_sprite(title, Rectangle {
+                x: (win_w - w) / 2.0,
+                y: (win_h - box_h) / 2.0 - margin_h - border_width - TITLE_MARGIN - h,
+                w: w,
+                h: h,
+            });
+        }
 
         for (i, action) in self.actions.iter().enumerate() {
 
@@ -142,7 +297,43 @@ impl View for MainMenuView {
                 h: h,
             });
         }
+    }
 
-        ViewAction::None
+    fn is_overlay(&self) -> bool {
+        self.paused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::phi::backend::DrawCall;
+    use ::phi::run_headless;
+    use ::phi::testing::headless;
+    use ::sdl2::pixels::Color;
+    use super::MainMenuView;
+
+    #[test]
+    fn draws_the_box_over_the_backgrounds() {
+        let calls = headless(1, |phi| {
+            run_headless(phi, 2, 60.0, |phi| Ok(Box::new(try!(MainMenuView::new(phi))))).unwrap()
+        });
+
+        assert_eq!(calls[0], DrawCall::Clear(Color::RGB(0, 0, 0)));
+        let fills: Vec<Color> = calls.iter().filter_map(|call| match *call {
+            DrawCall::FillRect(color, _) => Some(color),
+            _ => None,
+        }).collect();
+        assert_eq!(fills, vec![Color::RGB(70, 15, 70), Color::RGB(140, 30, 140)]);
+
+        // every action is drawn over the box, after the backgrounds
+        let box_at = calls.iter().position(|call| match *call {
+            DrawCall::FillRect(..) => true,
+            _ => false,
+        }).unwrap();
+        let labels = calls[box_at..].iter().filter(|call| match **call {
+            DrawCall::Copy { .. } => true,
+            _ => false,
+        }).count();
+        assert_eq!(labels, 4);
     }
 }
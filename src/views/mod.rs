@@ -1,3 +1,9 @@
 pub mod shared;
+pub mod controls;
+pub mod error;
 pub mod game;
+pub mod high_scores;
+pub mod hud;
 pub mod main_menu;
+pub mod name_entry;
+pub mod score;
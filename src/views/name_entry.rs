@@ -0,0 +1,200 @@
+use ::phi::{Error, Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::input::Action;
+use ::views::error::ErrorView;
+use ::views::game::MODE;
+use ::views::high_scores::{HighScores, HighScoresView, MAX_NAME_LEN};
+use ::views::shared::BackgroundSet;
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::pixels::Color;
+
+// Consts
+const FONT: &'static str = "assets/belligerent.ttf";
+const FONT_SIZE: i32 = 24;
+const TITLE_SIZE: i32 = 48;
+const LETTER_SIZE: i32 = 40;
+/// The characters a name can be made of, a blank first.
+const ALPHABET: &'static str = " ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const SLOT_W: f64 = 40.0;
+const SLOT_H: f64 = 56.0;
+const LINE_MARGIN: f64 = 24.0;
+
+/// Asks for the name to put next to a new high score, arcade style: up and
+/// down pick the letter under the cursor, left and right move the cursor.
+/// Letters and digits can also be typed directly.
+pub struct NameEntryView {
+    /// Indices in `ALPHABET`.
+    letters: Vec<usize>,
+    cursor: usize,
+    score: u64,
+    /// A sprite for every character of `ALPHABET` but the blank.
+    glyphs: Vec<Sprite>,
+    title: Sprite,
+    score_text: Sprite,
+    hint: Sprite,
+    bgs: BackgroundSet,
+}
+
+impl NameEntryView {
+    pub fn new(phi: &mut Phi, bgs: BackgroundSet, score: u64) -> Result<NameEntryView, Error> {
+        let mut glyphs = vec![];
+        for c in ALPHABET.chars().skip(1) {
+            glyphs.push(try!(phi.ttf_str_sprite(&c.to_string(), FONT, LETTER_SIZE, Color::RGB(255, 255, 255))));
+        }
+
+        let keys = phi.events.bindings.keys_label(Action::Confirm);
+        let hint = if keys.is_empty() {
+            "Confirm when you are done".to_string()
+        } else {
+            format!("Press {} when you are done", keys)
+        };
+
+        Ok(NameEntryView {
+            letters: vec![0; MAX_NAME_LEN],
+            cursor: 0,
+            score: score,
+            glyphs: glyphs,
+            title: try!(phi.ttf_str_sprite("New High Score!", FONT, TITLE_SIZE, Color::RGB(255, 255, 255))),
+            score_text: try!(phi.ttf_str_sprite(&format!("{} points", score), FONT, FONT_SIZE, Color::RGB(220, 220, 220))),
+            hint: try!(phi.ttf_str_sprite(&hint, FONT, FONT_SIZE, Color::RGB(220, 220, 220))),
+            bgs: bgs,
+        })
+    }
+
+    fn name(&self) -> String {
+        self.letters.iter()
+            .map(|&i| ALPHABET.as_bytes()[i] as char)
+            .collect()
+    }
+
+    /// Puts the score in the table, and shows it.
+    fn finish(&mut self, phi: &mut Phi) -> ViewAction {
+        let mut scores = HighScores::load();
+        let rank = scores.insert(MODE, &self.name(), self.score);
+
+        let view = match rank {
+            Some(rank) => {
+                // the row is only highlighted if the score was kept
+                let kept = match scores.save() {
+                    Ok(()) => Some(rank),
+                    Err(e) => { println!("{}", e); None },
+                };
+                HighScoresView::after_game(phi, self.bgs.clone(), MODE, kept, self.score)
+                    .map(|view| Box::new(view) as Box<View>)
+            },
+            None => ::views::main_menu::MainMenuView::game_over(phi, self.bgs.clone(), self.score)
+                .map(|view| Box::new(view) as Box<View>),
+        };
+
+        match view {
+            Ok(view) => ViewAction::Replace(view),
+            Err(e) => ErrorView::show(phi, e),
+        }
+    }
+}
+
+impl View for NameEntryView {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit;
+        }
+
+        self.bgs.update(dt);
+
+        if phi.events.pressed(Action::Confirm) || phi.events.pressed(Action::Pause) {
+            return self.finish(phi);
+        }
+
+        // keys which stand for a character of the alphabet type it
+        let typed: Vec<usize> = phi.events.now.pressed.iter()
+            .map(|key| key.name())
+            .filter(|name| name.chars().count() == 1)
+            .filter_map(|name| ALPHABET.find(&name.to_uppercase()[..]))
+            .collect();
+
+        for &letter in &typed {
+            self.letters[self.cursor] = letter;
+            self.cursor = ::std::cmp::min(self.cursor + 1, MAX_NAME_LEN - 1);
+        }
+
+        if phi.events.now.pressed.contains(&Keycode::Backspace) {
+            if self.letters[self.cursor] == 0 && self.cursor > 0 {
+                self.cursor -= 1;
+            }
+            self.letters[self.cursor] = 0;
+        }
+
+        // the typed keys may also be bound to actions
+        if !typed.is_empty() {
+            return ViewAction::None;
+        }
+
+        let letters = ALPHABET.len();
+        if phi.events.pressed(Action::MoveUp) {
+            self.letters[self.cursor] = (self.letters[self.cursor] + 1) % letters;
+        }
+        if phi.events.pressed(Action::MoveDown) {
+            self.letters[self.cursor] = (self.letters[self.cursor] + letters - 1) % letters;
+        }
+        if phi.events.pressed(Action::MoveLeft) && self.cursor > 0 {
+            self.cursor -= 1;
+        }
+        if phi.events.pressed(Action::MoveRight) && self.cursor + 1 < MAX_NAME_LEN {
+            self.cursor += 1;
+        }
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _alpha: f64) {
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        self.bgs.render(&mut *phi.renderer);
+
+        let (win_w, win_h) = phi.output_size();
+        let slots_w = SLOT_W * MAX_NAME_LEN as f64;
+        let slots_x = (win_w - slots_w) / 2.0;
+        let slots_y = (win_h - SLOT_H) / 2.0;
+
+        // the title and the score above the name, the hint below it
+        let (w, h) = self.score_text.size();
+        let score_y = slots_y - LINE_MARGIN - h;
+        phi.renderer.copy_sprite(&self.score_text, Rectangle { x: (win_w - w) / 2.0, y: score_y, w: w, h: h });
+
+        let (w, h) = self.title.size();
+        phi.renderer.copy_sprite(&self.title, Rectangle { x: (win_w - w) / 2.0, y: score_y - h, w: w, h: h });
+
+        let (w, h) = self.hint.size();
+        phi.renderer.copy_sprite(&self.hint, Rectangle {
+            x: (win_w - w) / 2.0,
+            y: slots_y + SLOT_H + LINE_MARGIN,
+            w: w,
+            h: h,
+        });
+
+        for (i, &letter) in self.letters.iter().enumerate() {
+            let x = slots_x + SLOT_W * i as f64;
+
+            if letter > 0 {
+                let glyph = &self.glyphs[letter - 1];
+                let (w, h) = glyph.size();
+                phi.renderer.copy_sprite(glyph, Rectangle {
+                    x: x + (SLOT_W - w) / 2.0,
+                    y: slots_y + (SLOT_H - h) / 2.0,
+                    w: w,
+                    h: h,
+                });
+            }
+
+            // every slot is underlined, the one under the cursor brighter
+            phi.renderer.set_draw_color(if i == self.cursor {
+                Color::RGB(255, 220, 60)
+            } else {
+                Color::RGB(120, 120, 120)
+            });
+            phi.renderer.fill_rect(Rectangle { x: x + 4.0, y: slots_y + SLOT_H - 4.0, w: SLOT_W - 8.0, h: 3.0 });
+        }
+    }
+}
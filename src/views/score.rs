@@ -0,0 +1,177 @@
+// Consts
+/// How long, in seconds, the next hit has to keep a chain going.
+const CHAIN_WINDOW: f64 = 1.5;
+/// How many hits in a chain raise the multiplier by one.
+const CHAIN_STEP: u32 = 5;
+const MAX_MULTIPLIER: u32 = 8;
+/// Awarded for every weapon used during a chain, beyond the first one.
+const VARIETY_BONUS: u64 = 250;
+
+
+/// Something which changed the score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScoreEvent {
+    /// `points` were added, bringing the score to `total`.
+    Scored { points: u64, total: u64 },
+    /// The multiplier changed, because a chain grew or was broken.
+    Multiplier(u32),
+}
+
+/// Keeps track of the score of a game. Hits which follow each other within
+/// `CHAIN_WINDOW` form a chain, which raises the multiplier of the points,
+/// and using several weapons during a chain earns a bonus.
+///
+/// Every change is sent as a `ScoreEvent` to each of the subscribers, so
+/// that the systems which follow the score can react to it without polling.
+pub struct Score {
+    total: u64,
+    /// How many hits the current chain has.
+    chain: u32,
+    /// How long until the chain is broken.
+    chain_left: f64,
+    /// The weapons which scored during the current chain.
+    weapons: Vec<u8>,
+    /// The events which every subscriber has yet to see, by subscriber.
+    queues: Vec<Vec<ScoreEvent>>,
+}
+
+/// Something which follows the events of a `Score`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Subscriber(usize);
+
+impl Score {
+    pub fn new() -> Score {
+        Score {
+            total: 0,
+            chain: 0,
+            chain_left: 0.0,
+            weapons: vec![],
+            queues: vec![],
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn multiplier(&self) -> u32 {
+        ::std::cmp::min(1 + self.chain / CHAIN_STEP, MAX_MULTIPLIER)
+    }
+
+    /// Breaks the chain once nothing was hit for `CHAIN_WINDOW`.
+    pub fn update(&mut self, dt: f64) {
+        if self.chain == 0 {
+            return;
+        }
+
+        self.chain_left -= dt;
+        if self.chain_left <= 0.0 {
+            let multiplier = self.multiplier();
+            self.chain = 0;
+            self.weapons.clear();
+            if multiplier != 1 {
+                self.send(ScoreEvent::Multiplier(1));
+            }
+        }
+    }
+
+    /// Awards `base` points, scaled by the multiplier, for something which
+    /// `weapon` destroyed.
+    pub fn hit(&mut self, base: u64, weapon: u8) {
+        let multiplier = self.multiplier();
+        self.chain += 1;
+        self.chain_left = CHAIN_WINDOW;
+        if self.multiplier() != multiplier {
+            let multiplier = self.multiplier();
+            self.send(ScoreEvent::Multiplier(multiplier));
+        }
+
+        let mut points = base * self.multiplier() as u64;
+        if !self.weapons.contains(&weapon) {
+            self.weapons.push(weapon);
+            points += VARIETY_BONUS * (self.weapons.len() as u64 - 1);
+        }
+
+        self.total += points;
+        let total = self.total;
+        self.send(ScoreEvent::Scored { points: points, total: total });
+    }
+
+    /// Starts sending the events which happen from now on to a new
+    /// subscriber.
+    pub fn subscribe(&mut self) -> Subscriber {
+        self.queues.push(vec![]);
+        Subscriber(self.queues.len() - 1)
+    }
+
+    /// The events which happened since `subscriber` last asked for them, or
+    /// since it subscribed, oldest first.
+    pub fn events(&mut self, subscriber: Subscriber) -> Vec<ScoreEvent> {
+        ::std::mem::replace(&mut self.queues[subscriber.0], vec![])
+    }
+
+    fn send(&mut self, event: ScoreEvent) {
+        for queue in &mut self.queues {
+            queue.push(event);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Score, ScoreEvent, CHAIN_STEP, CHAIN_WINDOW, VARIETY_BONUS};
+
+    #[test]
+    fn every_subscriber_sees_every_event() {
+        let mut score = Score::new();
+        let hud = score.subscribe();
+        let audio = score.subscribe();
+
+        score.hit(100, 1);
+        let scored = vec![ScoreEvent::Scored { points: 100, total: 100 }];
+        assert_eq!(score.events(hud), scored);
+        assert_eq!(score.events(hud), vec![]);
+
+        score.hit(10, 1);
+        assert_eq!(score.events(audio), vec![
+            ScoreEvent::Scored { points: 100, total: 100 },
+            ScoreEvent::Scored { points: 10, total: 110 },
+        ]);
+        assert_eq!(score.events(hud), vec![ScoreEvent::Scored { points: 10, total: 110 }]);
+    }
+
+    #[test]
+    fn late_subscribers_only_see_what_follows() {
+        let mut score = Score::new();
+        score.hit(100, 1);
+        let late = score.subscribe();
+        assert_eq!(score.events(late), vec![]);
+
+        score.hit(100, 1);
+        assert_eq!(score.events(late), vec![ScoreEvent::Scored { points: 100, total: 200 }]);
+    }
+
+    #[test]
+    fn chains_raise_the_multiplier_until_broken() {
+        let mut score = Score::new();
+        let subscriber = score.subscribe();
+
+        for _ in 0..CHAIN_STEP {
+            score.hit(10, 1);
+        }
+        assert_eq!(score.multiplier(), 2);
+        assert!(score.events(subscriber).contains(&ScoreEvent::Multiplier(2)));
+
+        // a second weapon during the chain earns a bonus
+        score.hit(10, 2);
+        assert_eq!(score.events(subscriber), vec![
+            ScoreEvent::Scored { points: 20 + VARIETY_BONUS, total: 10 * (CHAIN_STEP as u64 - 1) + 20 + 20 + VARIETY_BONUS },
+        ]);
+
+        score.update(CHAIN_WINDOW);
+        assert_eq!(score.multiplier(), 1);
+        assert_eq!(score.events(subscriber), vec![ScoreEvent::Multiplier(1)]);
+    }
+}
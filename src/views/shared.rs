@@ -1,6 +1,7 @@
+use ::phi::{Error, Phi};
+use ::phi::backend::Backend;
 use ::phi::data::Rectangle;
 use ::phi::gfx::{Sprite,CopySprite};
-use ::sdl2::render::{Renderer};
 
 #[derive(Clone)]
 pub struct Background {
@@ -10,23 +11,26 @@ pub struct Background {
 }
 
 impl Background {
-    pub fn render(&mut self, renderer: &mut Renderer, elapsed: f64) {
+    pub fn update(&mut self, dt: f64) {
         let size = self.sprite.size();
-        self.pos += self.vel * elapsed;
+        self.pos += self.vel * dt;
         if self.pos > size.0 {
             self.pos -= size.0;
         }
+    }
 
-        let (win_w, win_h) = renderer.output_size().unwrap();
-        let scale = win_h as f64 / size.1;
+    pub fn render(&self, renderer: &mut Backend) {
+        let size = self.sprite.size();
+        let (win_w, win_h) = renderer.output_size();
+        let scale = win_h / size.1;
 
         let mut physicall_left = -self.pos * scale;
-        while physicall_left < win_w as f64 {
+        while physicall_left < win_w {
             renderer.copy_sprite(&self.sprite, Rectangle {
                 x: physicall_left,
                 y: 0.0,
                 w: size.0 * scale,
-                h: win_h as f64,
+                h: win_h,
             });
             physicall_left += size.0 * scale;
         }
@@ -41,30 +45,36 @@ pub struct BackgroundSet {
 }
 
 impl BackgroundSet {
-    pub fn new(renderer: &mut Renderer) -> BackgroundSet {
-        BackgroundSet {
+    pub fn new(phi: &mut Phi) -> Result<BackgroundSet, Error> {
+        Ok(BackgroundSet {
             back: Background {
                 pos: 0.0,
                 vel: 20.0,
-                sprite: Sprite::load(renderer, "assets/starBG.png").unwrap(),
+                sprite: try!(phi.sprite("assets/starBG.png")),
             },
             middle: Background {
                 pos: 0.0,
                 vel: 40.0,
-                sprite: Sprite::load(renderer, "assets/starMG.png").unwrap(),
+                sprite: try!(phi.sprite("assets/starMG.png")),
             },
             front: Background {
                 pos: 0.0,
                 vel: 80.0,
-                sprite: Sprite::load(renderer, "assets/starFG.png").unwrap(),
+                sprite: try!(phi.sprite("assets/starFG.png")),
             },
-        }
+        })
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.back.update(dt);
+        self.middle.update(dt);
+        self.front.update(dt);
     }
 
-    pub fn render(&mut self, renderer: &mut Renderer, elapsed: f64) {
-        self.back.render(renderer, elapsed);
-        self.middle.render(renderer, elapsed);
-        self.front.render(renderer, elapsed);
+    pub fn render(&self, renderer: &mut Backend) {
+        self.back.render(renderer);
+        self.middle.render(renderer);
+        self.front.render(renderer);
     }
 
 }